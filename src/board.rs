@@ -0,0 +1,1150 @@
+use itertools::Itertools;
+use std::cmp::min;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+pub type Point = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Unflagged,
+    Unsure,
+    Sure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellType {
+    Empty { adjacent_mines: u8 },
+    Mine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellState {
+    Opened,
+    Unopened(Flag),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub cell_type: CellType,
+    pub state: CellState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress,
+    Lost,
+    Won,
+}
+
+// A cell as the player sees it, closed cells hiding what they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellView {
+    Closed(Flag),
+    Number(u8),
+    // A mine opened in lives mode
+    Mine,
+    // The mine that lost the game
+    ExplodedMine,
+    // Any other mine left unflagged once the game is lost
+    RevealedMine,
+    // Outside the play area of a masked board
+    Wall,
+    // An opened safe cell too far from the last open to show its number,
+    // under Rules.fog_radius
+    Opened,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom {
+        width: usize,
+        height: usize,
+        mines: usize,
+    },
+}
+
+impl Difficulty {
+    // Width, height and mine count
+    pub fn params(&self) -> (usize, usize, usize) {
+        match *self {
+            Difficulty::Beginner => (9, 9, 10),
+            Difficulty::Intermediate => (16, 16, 40),
+            Difficulty::Expert => (30, 16, 99),
+            Difficulty::Custom {
+                width,
+                height,
+                mines,
+            } => (width, height, mines),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Beginner => write!(f, "Beginner"),
+            Difficulty::Intermediate => write!(f, "Intermediate"),
+            Difficulty::Expert => write!(f, "Expert"),
+            Difficulty::Custom {
+                width,
+                height,
+                mines,
+            } => write!(f, "Custom {}x{}, {} mines", width, height, mines),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MineError {
+    EmptyBoard,
+    BoardTooLarge { width: usize, height: usize },
+    TooManyMines { mines: usize, cells: usize },
+    OutOfBounds(Point),
+    Cancelled,
+    NoSolvableLayout { attempts: usize },
+    NoSymmetricLayout { mines: usize },
+    InvalidScript { line: usize, reason: String },
+    InvalidSave { line: usize, reason: String },
+    UnsupportedVersion { version: u32, supported: u32 },
+    PreOpenedMine(Point),
+    DuplicateMine(Point),
+    InvalidPlacement { expected: usize, placed: usize },
+    LayoutMismatch,
+    InvalidWeights(String),
+    InvalidMask(String),
+    InvalidState(String),
+    InvalidPuzzleCode(String),
+    InvalidRender { line: usize, reason: String },
+}
+
+impl fmt::Display for MineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MineError::EmptyBoard => write!(f, "board must be at least 1x1"),
+            MineError::BoardTooLarge { width, height } => {
+                write!(f, "{}x{} board has too many cells to count", width, height)
+            }
+            MineError::TooManyMines { mines, cells } => write!(
+                f,
+                "{} mines do not fit in {} cells, at least one cell must stay safe",
+                mines, cells
+            ),
+            MineError::OutOfBounds((x, y)) => write!(f, "({}, {}) is outside the board", x, y),
+            MineError::Cancelled => write!(f, "generation was cancelled"),
+            MineError::NoSolvableLayout { attempts } => write!(
+                f,
+                "no layout solvable without guessing found in {} attempts",
+                attempts
+            ),
+            MineError::NoSymmetricLayout { mines } => {
+                write!(f, "{} mines can not be laid out symmetrically", mines)
+            }
+            MineError::InvalidScript { line, reason } => write!(f, "line {}: {}", line, reason),
+            MineError::InvalidSave { line, reason } => {
+                write!(f, "save line {}: {}", line, reason)
+            }
+            MineError::UnsupportedVersion { version, supported } => write!(
+                f,
+                "save version {} is newer than the supported version {}",
+                version, supported
+            ),
+            MineError::PreOpenedMine((x, y)) => {
+                write!(
+                    f,
+                    "({}, {}) is a mine and can not be opened in advance",
+                    x, y
+                )
+            }
+            MineError::DuplicateMine((x, y)) => {
+                write!(f, "({}, {}) is listed as a mine more than once", x, y)
+            }
+            MineError::InvalidPlacement { expected, placed } => write!(
+                f,
+                "placer laid {} distinct mines instead of {}",
+                placed, expected
+            ),
+            MineError::LayoutMismatch => write!(f, "boards have different mine layouts"),
+            MineError::InvalidWeights(reason) => write!(f, "invalid mine weights: {}", reason),
+            MineError::InvalidMask(reason) => write!(f, "invalid mask: {}", reason),
+            MineError::InvalidState(reason) => write!(f, "invalid state: {}", reason),
+            MineError::InvalidPuzzleCode(reason) => write!(f, "invalid puzzle code: {}", reason),
+            MineError::InvalidRender { line, reason } => {
+                write!(f, "render line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MineError {}
+
+pub fn validate_params(width: usize, height: usize, mines: usize) -> Result<(), MineError> {
+    if width == 0 || height == 0 {
+        return Err(MineError::EmptyBoard);
+    }
+    let cells = width
+        .checked_mul(height)
+        .ok_or(MineError::BoardTooLarge { width, height })?;
+    if mines >= cells {
+        return Err(MineError::TooManyMines { mines, cells });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CascadeRule {
+    // Cascades open zeros and stop at the first ring of numbers around them
+    #[default]
+    StopAtNumbers,
+    // Numbers reached by a cascade also open their safe neighbors, but those
+    // extra cells never cascade further, even when they are zeros
+    OpenOneMore,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CascadeFlagPolicy {
+    // Cascades stop at every flagged cell
+    #[default]
+    RespectAllFlags,
+    // Cascades open Unsure cells and only stop at Sure flags
+    RespectSureOnly,
+}
+
+impl CascadeFlagPolicy {
+    pub(crate) fn opens(&self, state: CellState) -> bool {
+        match state {
+            CellState::Unopened(Flag::Unflagged) => true,
+            CellState::Unopened(Flag::Unsure) => {
+                matches!(self, CascadeFlagPolicy::RespectSureOnly)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Variant rules, the defaults being classic minesweeper. The win threshold
+// is a float, so rules and states compare with PartialEq only, and by the
+// threshold they play with: NaN and 1.5 equal 1.0.
+#[derive(Debug, Clone, Copy)]
+pub struct Rules {
+    pub cascade_rule: CascadeRule,
+    pub cascade_flag_policy: CascadeFlagPolicy,
+    pub practice_mode: PracticeMode,
+    pub win_condition: WinCondition,
+    pub feedback_on_misflag: MisflagFeedback,
+    // Opening clicks allowed before the game is lost, a whole cascade or
+    // chord being one click. None means no limit.
+    pub max_opens: Option<u32>,
+    pub open_flag_policy: OpenFlagPolicy,
+    pub flag_mines_on_win: WinFlags,
+    // Opened cells further than this from the last opening click show without
+    // their number, by Chebyshev distance. None shows every number, and so
+    // does a board nobody has clicked since it was made or loaded.
+    pub fog_radius: Option<usize>,
+    // Share of the safe cells, from 0.0 to 1.0, that must be opened to win.
+    // Below 1.0 a huge board can be won without clearing it, and the rest
+    // of the win condition still applies. NaN or anything above 1.0 asks
+    // for every safe cell, and 0.0 or below for just one.
+    pub win_threshold: f64,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            cascade_rule: CascadeRule::default(),
+            cascade_flag_policy: CascadeFlagPolicy::default(),
+            practice_mode: PracticeMode::default(),
+            win_condition: WinCondition::default(),
+            feedback_on_misflag: MisflagFeedback::default(),
+            max_opens: None,
+            open_flag_policy: OpenFlagPolicy::default(),
+            flag_mines_on_win: WinFlags::default(),
+            fog_radius: None,
+            win_threshold: 1.0,
+        }
+    }
+}
+
+impl Rules {
+    // The win threshold as it is played, from 0.0 to 1.0. NaN and anything
+    // above 1.0 count as 1.0, anything below 0.0 as 0.0.
+    pub fn effective_win_threshold(&self) -> f64 {
+        match self.win_threshold {
+            threshold if threshold.is_nan() => 1.0,
+            threshold => threshold.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl PartialEq for Rules {
+    fn eq(&self, other: &Rules) -> bool {
+        self.cascade_rule == other.cascade_rule
+            && self.cascade_flag_policy == other.cascade_flag_policy
+            && self.practice_mode == other.practice_mode
+            && self.win_condition == other.win_condition
+            && self.feedback_on_misflag == other.feedback_on_misflag
+            && self.max_opens == other.max_opens
+            && self.open_flag_policy == other.open_flag_policy
+            && self.flag_mines_on_win == other.flag_mines_on_win
+            && self.fog_radius == other.fog_radius
+            && self.effective_win_threshold() == other.effective_win_threshold()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WinCondition {
+    // Opening every safe cell wins, flags don't matter
+    #[default]
+    OpenAllSafe,
+    // Every mine must also carry a Sure flag, with no Sure flag on a safe cell
+    FlagAllMines,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PracticeMode {
+    // Opening a mine costs a life
+    #[default]
+    Off,
+    // Opening a mine flags it Sure and counts a mistake instead, so the game
+    // always goes on until every safe cell is opened
+    On,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenFlagPolicy {
+    // Opening a Sure flagged cell does nothing, Unsure cells still open
+    #[default]
+    ProtectSure,
+    // Any closed cell opens, flagged or not
+    OpenAll,
+}
+
+impl OpenFlagPolicy {
+    pub(crate) fn opens(&self, state: CellState) -> bool {
+        match state {
+            CellState::Unopened(Flag::Sure) => matches!(self, OpenFlagPolicy::OpenAll),
+            CellState::Unopened(_) => true,
+            CellState::Opened => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WinFlags {
+    // Flags stay as the player left them when the game is won
+    #[default]
+    Keep,
+    // Every mine still closed gets a Sure flag on the win, for a tidy board
+    FlagMines,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MisflagFeedback {
+    // A Sure flag on a safe cell is left for the player to find out about
+    #[default]
+    Off,
+    // A Sure flag on a safe cell opens one of its closed unflagged safe
+    // neighbors as a nudge, a zero when there is one
+    On,
+}
+
+pub(crate) fn check_mask(
+    width: usize,
+    height: usize,
+    mask: Option<&Vec<Vec<bool>>>,
+) -> Result<(), MineError> {
+    match mask {
+        Some(mask) if mask.len() != height || mask.iter().any(|row| row.len() != width) => Err(
+            MineError::InvalidMask(format!("mask must be {} rows of {}", height, width)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+// Every cell's neighbors, worked out once per board size and mask. Walls,
+// the cells a mask leaves out, are nobody's neighbors and have none.
+pub(crate) struct Neighbors {
+    width: usize,
+    lists: Vec<Vec<Point>>,
+    mask: Option<Vec<Vec<bool>>>,
+}
+
+impl Neighbors {
+    fn new(width: usize, height: usize, mask: Option<Vec<Vec<bool>>>) -> Neighbors {
+        let playable = |(x, y): Point| mask.as_ref().is_none_or(|mask| mask[y][x]);
+        let lists = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|point| match playable(point) {
+                true => find_adjacent(point.0, point.1, width, height)
+                    .into_iter()
+                    .filter(|&adj| playable(adj))
+                    .collect(),
+                false => Vec::new(),
+            })
+            .collect();
+        Neighbors { width, lists, mask }
+    }
+
+    pub(crate) fn of(&self, point: Point) -> &[Point] {
+        let (x, y) = point;
+        &self.lists[y * self.width + x]
+    }
+
+    fn is_wall(&self, point: Point) -> bool {
+        let (x, y) = point;
+        self.mask.as_ref().is_some_and(|mask| !mask[y][x])
+    }
+}
+
+impl fmt::Debug for Neighbors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Neighbors {{ .. }}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    cells: Vec<Vec<Cell>>,
+    // Shared between clones, the neighbors only depend on the dimensions and
+    // the mask
+    pub(crate) neighbors: Arc<Neighbors>,
+    // Tick at which each cell was opened, row-major, for replay animations
+    open_order: Vec<Option<u32>>,
+    pub(crate) ticks: u32,
+    pub(crate) last_click: Option<Point>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, cell: Cell) -> Grid {
+        Grid::masked(width, height, cell, None)
+    }
+
+    // The mask is indexed [y][x] and must cover the board, checked by callers
+    fn masked(width: usize, height: usize, cell: Cell, mask: Option<Vec<Vec<bool>>>) -> Grid {
+        Grid {
+            cells: vec![vec![cell; width]; height],
+            neighbors: Arc::new(Neighbors::new(width, height, mask)),
+            open_order: vec![None; width * height],
+            ticks: 0,
+            last_click: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn in_bounds(&self, point: Point) -> bool {
+        let (x, y) = point;
+        x < self.width() && y < self.height()
+    }
+
+    pub fn get(&self, point: Point) -> Option<&Cell> {
+        let (x, y) = point;
+        self.cells.get(y).and_then(|row| row.get(x))
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut Cell> {
+        let (x, y) = point;
+        self.cells.get_mut(y).and_then(|row| row.get_mut(x))
+    }
+
+    pub fn adjacent(&self, point: Point) -> &[Point] {
+        self.neighbors.of(point)
+    }
+
+    // The play area, indexed [y][x], None when the whole rectangle is in play
+    pub fn mask(&self) -> Option<&Vec<Vec<bool>>> {
+        self.neighbors.mask.as_ref()
+    }
+
+    // Walls are kept as opened zeros without neighbors, so they never take a
+    // mine, never count toward a number and never stand between the player
+    // and a win
+    pub fn is_wall(&self, point: Point) -> bool {
+        self.neighbors.is_wall(point)
+    }
+
+    // Row by row, left to right
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width();
+        (0..self.height()).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter().flatten()
+    }
+
+    // Opens the cell and stamps it with the next tick. Cells are only stamped
+    // once, so the ticks of a board count up from 0 without gaps.
+    pub fn open(&mut self, point: Point) {
+        let (x, y) = point;
+        let index = y * self.width() + x;
+        if let CellState::Unopened(_) = self.cells[y][x].state {
+            self.cells[y][x].state = CellState::Opened;
+            self.open_order[index] = Some(self.ticks);
+            self.ticks += 1;
+        }
+    }
+
+    // Whether any playable cell is opened, walls being opened from the start
+    pub(crate) fn any_opened(&self) -> bool {
+        self.points()
+            .any(|point| self[point].state == CellState::Opened && !self.is_wall(point))
+    }
+
+    // The cell the latest opening click landed on, where its cascade started
+    pub fn last_click(&self) -> Option<Point> {
+        self.last_click
+    }
+
+    // None for closed cells and for cells opened by writing the state directly
+    pub fn open_order(&self, point: Point) -> Option<u32> {
+        let (x, y) = point;
+        match self.in_bounds(point) {
+            true => self.open_order[y * self.width() + x],
+            false => None,
+        }
+    }
+
+    pub fn pack(&self) -> PackedGrid {
+        PackedGrid {
+            width: self.width(),
+            height: self.height(),
+            cells: self.iter().map(|&cell| PackedCell::from(cell)).collect(),
+            mask: self.mask().cloned(),
+        }
+    }
+}
+
+// Grids are equal when every cell, number included, and the walls are. When
+// cells were opened is history that saves and packed grids don't keep, so it
+// is left out and a board compares equal to itself after a round trip.
+impl PartialEq for Grid {
+    fn eq(&self, other: &Grid) -> bool {
+        self.cells == other.cells && self.mask() == other.mask()
+    }
+}
+
+impl Eq for Grid {}
+
+impl Index<Point> for Grid {
+    type Output = Cell;
+
+    fn index(&self, point: Point) -> &Cell {
+        let (x, y) = point;
+        &self.cells[y][x]
+    }
+}
+
+impl IndexMut<Point> for Grid {
+    fn index_mut(&mut self, point: Point) -> &mut Cell {
+        let (x, y) = point;
+        &mut self.cells[y][x]
+    }
+}
+
+// A cell in one byte, for storing many boards. Bits 0-3 hold the number of
+// a safe cell, bit 4 is set for a mine, bit 5 for an opened cell and bits
+// 6-7 hold the flag of a closed one: 0 none, 1 unsure, 2 sure. Numbers only
+// go up to 8, so every real cell fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedCell(u8);
+
+const PACKED_MINE: u8 = 1 << 4;
+
+const PACKED_OPENED: u8 = 1 << 5;
+
+impl PackedCell {
+    pub fn byte(self) -> u8 {
+        self.0
+    }
+
+    pub fn is_mine(self) -> bool {
+        self.0 & PACKED_MINE != 0
+    }
+
+    pub fn adjacent_mines(self) -> u8 {
+        self.0 & 0x0f
+    }
+
+    pub fn cell_type(self) -> CellType {
+        match self.is_mine() {
+            true => CellType::Mine,
+            false => CellType::Empty {
+                adjacent_mines: self.adjacent_mines(),
+            },
+        }
+    }
+
+    pub fn state(self) -> CellState {
+        match (self.0 & PACKED_OPENED != 0, self.0 >> 6) {
+            (true, _) => CellState::Opened,
+            (false, 0) => CellState::Unopened(Flag::Unflagged),
+            (false, 1) => CellState::Unopened(Flag::Unsure),
+            (false, _) => CellState::Unopened(Flag::Sure),
+        }
+    }
+
+    pub fn cell(self) -> Cell {
+        Cell {
+            cell_type: self.cell_type(),
+            state: self.state(),
+        }
+    }
+}
+
+impl From<Cell> for PackedCell {
+    fn from(cell: Cell) -> PackedCell {
+        debug_assert!(
+            !matches!(
+                cell.cell_type,
+                CellType::Empty {
+                    adjacent_mines: 16..
+                }
+            ),
+            "number too big to pack"
+        );
+        let kind = match cell.cell_type {
+            CellType::Empty { adjacent_mines } => adjacent_mines & 0x0f,
+            CellType::Mine => PACKED_MINE,
+        };
+        let state = match cell.state {
+            CellState::Opened => PACKED_OPENED,
+            CellState::Unopened(Flag::Unflagged) => 0,
+            CellState::Unopened(Flag::Unsure) => 1 << 6,
+            CellState::Unopened(Flag::Sure) => 2 << 6,
+        };
+        PackedCell(kind | state)
+    }
+}
+
+// A board's cells packed a byte each in row order, a third the size of the
+// cells in a Grid. It leaves out the neighbor table and open order, and
+// keeps the mask of a walled board so the walls come back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<PackedCell>,
+    mask: Option<Vec<Vec<bool>>>,
+}
+
+impl PackedGrid {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, point: Point) -> Option<Cell> {
+        let (x, y) = point;
+        match x < self.width && y < self.height {
+            true => Some(self.cells[y * self.width + x].cell()),
+            false => None,
+        }
+    }
+
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.cells.iter().map(|cell| cell.byte())
+    }
+
+    // Opened cells come back without an open order
+    pub fn unpack(&self) -> Grid {
+        let mut grid = Grid::masked(
+            self.width,
+            self.height,
+            Cell {
+                cell_type: CellType::Empty { adjacent_mines: 0 },
+                state: CellState::Unopened(Flag::Unflagged),
+            },
+            self.mask.clone(),
+        );
+        for point in grid.points().collect::<Vec<Point>>() {
+            let (x, y) = point;
+            grid[point] = self.cells[y * self.width + x].cell();
+        }
+        grid
+    }
+}
+
+// A cell's neighbors by state, each list in neighbor order. Every neighbor is
+// in exactly one list, unopened holding the closed cells with no flag.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NeighborSummary {
+    pub opened: Vec<Point>,
+    pub unopened: Vec<Point>,
+    pub sure: Vec<Point>,
+    pub unsure: Vec<Point>,
+}
+
+impl NeighborSummary {
+    // Closed neighbors, flagged or not
+    pub fn closed(&self) -> usize {
+        self.unopened.len() + self.sure.len() + self.unsure.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameState {
+    pub status: GameStatus,
+    pub grid: Grid,
+    pub lives: u32,
+    // Mines opened in practice mode
+    pub mistakes: u32,
+    // Opening clicks made, counted against Rules.max_opens
+    pub opens: u32,
+    // The mine opened with the last life
+    pub lost_at: Option<Point>,
+    pub rules: Rules,
+}
+
+impl GameState {
+    pub fn empty(width: usize, height: usize) -> GameState {
+        GameState::filled(
+            width,
+            height,
+            Cell {
+                cell_type: CellType::Empty { adjacent_mines: 0 },
+                state: CellState::Unopened(Flag::Unflagged),
+            },
+        )
+    }
+
+    pub fn filled(width: usize, height: usize, cell: Cell) -> GameState {
+        GameState {
+            status: GameStatus::InProgress,
+            grid: Grid::new(width, height, cell),
+            lives: 1,
+            mistakes: 0,
+            opens: 0,
+            lost_at: None,
+            rules: Rules::default(),
+        }
+    }
+
+    // Opening clicks left under Rules.max_opens, None when there is no limit
+    pub fn opens_left(&self) -> Option<u32> {
+        self.rules
+            .max_opens
+            .map(|max_opens| max_opens.saturating_sub(self.opens))
+    }
+
+    // No mines at all, so the first click opens the whole board and wins.
+    // Handy for working on a front end.
+    pub fn sandbox(width: usize, height: usize) -> GameState {
+        GameState::from_mines(width, height, &[])
+    }
+
+    // A point listed more than once is a single mine
+    pub fn from_mines(width: usize, height: usize, mines: &[Point]) -> GameState {
+        GameState::empty(width, height).place_mines(mines)
+    }
+
+    // A board whose play area is the true cells of the mask, indexed [y][x].
+    // The false cells are walls: never mines, never opened by the player and
+    // left out of every number and of the win condition.
+    pub fn from_mines_masked(
+        width: usize,
+        height: usize,
+        mines: &[Point],
+        mask: Option<Vec<Vec<bool>>>,
+    ) -> Result<GameState, MineError> {
+        check_mask(width, height, mask.as_ref())?;
+        if let Some(&point) = mines.iter().find(|&&(x, y)| x >= width || y >= height) {
+            return Err(MineError::OutOfBounds(point));
+        }
+        if let Some(&(x, y)) = mines
+            .iter()
+            .find(|&&(x, y)| mask.as_ref().is_some_and(|mask| !mask[y][x]))
+        {
+            return Err(MineError::InvalidMask(format!(
+                "({}, {}) is a wall and can not take a mine",
+                x, y
+            )));
+        }
+
+        let empty = GameState::empty(width, height);
+        let grid = Grid::masked(width, height, empty.grid[(0, 0)], mask);
+        let mut state = GameState { grid, ..empty }.place_mines(mines);
+        for point in state.grid.points().collect::<Vec<Point>>() {
+            if state.grid.is_wall(point) {
+                state.grid[point].state = CellState::Opened;
+            }
+        }
+        Ok(state)
+    }
+
+    // Lays mines on a fresh empty state. Taking the state rather than the
+    // dimensions lets a batch of boards share one neighbor table.
+    pub(crate) fn place_mines(self, mines: &[Point]) -> GameState {
+        let mut grid = self.grid;
+
+        // Inject mines, a point listed twice being one mine
+        for &mine_point in mines.iter() {
+            let mine = grid[mine_point];
+            if let CellType::Mine = mine.cell_type {
+                continue;
+            }
+            grid[mine_point] = Cell {
+                cell_type: CellType::Mine,
+                ..mine
+            };
+
+            // Increment adjacent_mines count for adjacent cells
+            let neighbors = Arc::clone(&grid.neighbors);
+            for &point in neighbors.of(mine_point) {
+                let cell = grid[point];
+                match cell.cell_type {
+                    CellType::Empty { adjacent_mines } => {
+                        grid[point] = Cell {
+                            cell_type: CellType::Empty {
+                                adjacent_mines: adjacent_mines + 1,
+                            },
+                            ..cell
+                        }
+                    }
+                    CellType::Mine => (),
+                }
+            }
+        }
+
+        GameState { grid, ..self }
+    }
+
+    pub fn mine_positions(&self) -> Vec<Point> {
+        self.grid
+            .points()
+            .filter(|&point| matches!(self.grid[point].cell_type, CellType::Mine))
+            .collect()
+    }
+
+    // The classic mine counter, which goes negative when over-flagged
+    pub fn remaining_mines(&self) -> isize {
+        let mines = self.mine_positions().len() as isize;
+        let flags = self
+            .grid
+            .iter()
+            .filter(|cell| matches!(cell.state, CellState::Unopened(Flag::Sure)))
+            .count() as isize;
+        mines - flags
+    }
+
+    // What the player can see, per cell: -1 closed, -2 flagged, -3 a mine opened
+    // in lives mode, -4 a wall, otherwise the opened number. Closed mines never
+    // show.
+    pub fn observation(&self) -> Vec<Vec<i8>> {
+        (0..self.grid.height())
+            .map(|y| {
+                (0..self.grid.width())
+                    .map(|x| match self.grid[(x, y)] {
+                        _ if self.grid.is_wall((x, y)) => -4,
+                        Cell {
+                            state: CellState::Unopened(Flag::Unflagged),
+                            ..
+                        } => -1,
+                        Cell {
+                            state: CellState::Unopened(_),
+                            ..
+                        } => -2,
+                        Cell {
+                            cell_type: CellType::Mine,
+                            state: CellState::Opened,
+                        } => -3,
+                        Cell {
+                            cell_type: CellType::Empty { adjacent_mines },
+                            state: CellState::Opened,
+                        } => adjacent_mines as i8,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The player view of every cell, indexed [y][x]
+    pub fn board_view(&self) -> Vec<Vec<CellView>> {
+        let right = self.grid.width().saturating_sub(1);
+        let bottom = self.grid.height().saturating_sub(1);
+        self.view_region((0, 0), (right, bottom))
+    }
+
+    // Whether the fog hides the number of an opened cell at the point
+    fn fogged(&self, point: Point) -> bool {
+        let (x, y) = point;
+        match (self.rules.fog_radius, self.grid.last_click()) {
+            (Some(radius), Some((last_x, last_y))) => {
+                x.abs_diff(last_x).max(y.abs_diff(last_y)) > radius
+            }
+            _ => false,
+        }
+    }
+
+    // A lost game shows the mines the player didn't flag, and which one was hit
+    pub(crate) fn view_of(&self, point: Point) -> CellView {
+        let cell = self.grid[point];
+        match (cell.cell_type, cell.state) {
+            _ if self.grid.is_wall(point) => CellView::Wall,
+            (CellType::Empty { .. }, CellState::Opened) if self.fogged(point) => CellView::Opened,
+            _ if self.lost_at == Some(point) => CellView::ExplodedMine,
+            (CellType::Mine, CellState::Unopened(Flag::Unflagged | Flag::Unsure))
+                if self.status == GameStatus::Lost =>
+            {
+                CellView::RevealedMine
+            }
+            (_, CellState::Unopened(flag)) => CellView::Closed(flag),
+            (CellType::Empty { adjacent_mines }, CellState::Opened) => {
+                CellView::Number(adjacent_mines)
+            }
+            (CellType::Mine, CellState::Opened) => CellView::Mine,
+        }
+    }
+
+    // The player view of the rectangle between the two corners, both included,
+    // indexed [y][x] from the top left corner. The rectangle is clipped to the
+    // board, so a window hanging off it comes back smaller or empty.
+    pub fn view_region(&self, top_left: Point, bottom_right: Point) -> Vec<Vec<CellView>> {
+        let (left, top) = top_left;
+        let right = min(bottom_right.0 + 1, self.grid.width());
+        let bottom = min(bottom_right.1 + 1, self.grid.height());
+        if left >= right {
+            return Vec::new();
+        }
+        (top..bottom)
+            .map(|y| (left..right).map(|x| self.view_of((x, y))).collect())
+            .collect()
+    }
+
+    // The answer, indexed [y][x]: every number and every mine whatever the
+    // player opened, the mine that lost the game marked, for reviewing a
+    // finished game
+    pub fn solution_view(&self) -> Vec<Vec<CellView>> {
+        (0..self.grid.height())
+            .map(|y| {
+                (0..self.grid.width())
+                    .map(|x| match self.grid[(x, y)].cell_type {
+                        _ if self.grid.is_wall((x, y)) => CellView::Wall,
+                        _ if self.lost_at == Some((x, y)) => CellView::ExplodedMine,
+                        CellType::Mine => CellView::Mine,
+                        CellType::Empty { adjacent_mines } => CellView::Number(adjacent_mines),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Every mine flagged Sure and no Sure flag on a safe cell, whatever the status
+    pub fn is_fully_marked(&self) -> bool {
+        self.grid.iter().all(|cell| match cell.cell_type {
+            CellType::Mine => cell.state == CellState::Unopened(Flag::Sure),
+            CellType::Empty { .. } => cell.state != CellState::Unopened(Flag::Sure),
+        })
+    }
+
+    pub fn total_cells(&self) -> usize {
+        self.grid.width() * self.grid.height()
+    }
+
+    // Safe cells the player still has to open
+    pub fn safe_cells_remaining(&self) -> usize {
+        self.grid
+            .iter()
+            .filter(|cell| {
+                matches!(
+                    cell,
+                    Cell {
+                        cell_type: CellType::Empty { .. },
+                        state: CellState::Unopened(_),
+                    }
+                )
+            })
+            .count()
+    }
+
+    // Closed, unflagged cells next to an opened number, in row order
+    pub fn frontier(&self) -> Vec<Point> {
+        self.grid
+            .points()
+            .filter(|&point| matches!(self.grid[point].state, CellState::Unopened(Flag::Unflagged)))
+            .filter(|&point| {
+                self.grid.adjacent(point).iter().any(|&adj| {
+                    matches!(
+                        self.grid[adj],
+                        Cell {
+                            cell_type: CellType::Empty {
+                                adjacent_mines: 1..
+                            },
+                            state: CellState::Opened,
+                        }
+                    )
+                })
+            })
+            .collect()
+    }
+
+    // The neighbors of a point split by what the player sees, in one pass
+    pub fn neighbor_summary(&self, point: Point) -> NeighborSummary {
+        let mut summary = NeighborSummary::default();
+        for &adj in self.grid.adjacent(point) {
+            match self.grid[adj].state {
+                CellState::Opened => summary.opened.push(adj),
+                CellState::Unopened(Flag::Unflagged) => summary.unopened.push(adj),
+                CellState::Unopened(Flag::Sure) => summary.sure.push(adj),
+                CellState::Unopened(Flag::Unsure) => summary.unsure.push(adj),
+            }
+        }
+        summary
+    }
+
+    // Closed neighbors of an opened number that aren't flagged Sure, the ones
+    // still in play around it. Anything but an opened number has none.
+    pub fn risky_neighbors(&self, point: Point) -> Vec<Point> {
+        match self.grid[point] {
+            Cell {
+                cell_type:
+                    CellType::Empty {
+                        adjacent_mines: 1..,
+                    },
+                state: CellState::Opened,
+            } => self
+                .grid
+                .adjacent(point)
+                .iter()
+                .copied()
+                .filter(|&adj| {
+                    matches!(
+                        self.grid[adj].state,
+                        CellState::Unopened(Flag::Unflagged | Flag::Unsure)
+                    )
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // A quarter turn clockwise, so a row of the old board becomes a column
+    pub fn rotate90(&self) -> GameState {
+        let height = self.grid.height();
+        self.remap(height, self.grid.width(), |(x, y)| (height - 1 - y, x))
+    }
+
+    // Mirrors left to right
+    pub fn flip_horizontal(&self) -> GameState {
+        let width = self.grid.width();
+        self.remap(width, self.grid.height(), |(x, y)| (width - 1 - x, y))
+    }
+
+    // Mirrors top to bottom
+    pub fn flip_vertical(&self) -> GameState {
+        let height = self.grid.height();
+        self.remap(self.grid.width(), height, |(x, y)| (x, height - 1 - y))
+    }
+
+    // Moves every cell to its new position on a board of the new size. The
+    // numbers don't change since these moves keep neighbors together, and
+    // cell states, open order and the rest of the state carry over.
+    fn remap(&self, width: usize, height: usize, to: impl Fn(Point) -> Point) -> GameState {
+        let mines = self
+            .mine_positions()
+            .into_iter()
+            .map(&to)
+            .collect::<Vec<Point>>();
+        let mask = self.grid.mask().map(|_| {
+            let mut mask = vec![vec![true; width]; height];
+            for point in self.grid.points().filter(|&point| self.grid.is_wall(point)) {
+                let (x, y) = to(point);
+                mask[y][x] = false;
+            }
+            mask
+        });
+        let mut grid = GameState::from_mines_masked(width, height, &mines, mask)
+            .expect("moved walls keep clear of the moved mines")
+            .grid;
+        for point in self.grid.points() {
+            let (x, y) = to(point);
+            grid[(x, y)].state = self.grid[point].state;
+            grid.open_order[y * width + x] = self.grid.open_order(point);
+        }
+        grid.ticks = self.grid.ticks;
+        grid.last_click = self.grid.last_click.map(&to);
+        GameState {
+            status: self.status,
+            grid,
+            lives: self.lives,
+            mistakes: self.mistakes,
+            opens: self.opens,
+            lost_at: self.lost_at.map(&to),
+            rules: self.rules,
+        }
+    }
+}
+
+pub fn find_adjacent(x: usize, y: usize, width: usize, height: usize) -> Vec<Point> {
+    let xs = (if x == 0 { 0 } else { x - 1 }..min(x + 2, width)).collect::<Vec<usize>>();
+    let ys = (if y == 0 { 0 } else { y - 1 }..min(y + 2, height)).collect::<Vec<usize>>();
+
+    xs.into_iter()
+        .cartesian_product(ys)
+        .filter(|&(adj_x, adj_y)| x != adj_x || y != adj_y)
+        .collect()
+}
+
+// The number of a cell counted straight from the layout rather than read from
+// the stored cell, so the two can be checked against each other. A mine gets
+// the count of the mines around it, and a wall 0 like every wall is stored.
+pub fn compute_cell_adjacency(state: &GameState, point: Point) -> u8 {
+    let grid = &state.grid;
+    if grid.is_wall(point) {
+        return 0;
+    }
+    find_adjacent(point.0, point.1, grid.width(), grid.height())
+        .into_iter()
+        .filter(|&adj| grid[adj].cell_type == CellType::Mine)
+        .count() as u8
+}
+
+// The mines around every cell, indexed [y][x]: the stored number for empty
+// cells and the same count for mines, so clusters show up. Walls are 0.
+pub fn density_map(state: &GameState) -> Vec<Vec<u8>> {
+    (0..state.grid.height())
+        .map(|y| {
+            (0..state.grid.width())
+                .map(|x| compute_cell_adjacency(state, (x, y)))
+                .collect()
+        })
+        .collect()
+}
+
+// The cells that differ between two states of one board, in row order, for
+// catching up after a move, as IncrementalSolver does
+pub fn changed_cells(before: &GameState, after: &GameState) -> Vec<Point> {
+    after
+        .grid
+        .points()
+        .filter(|&point| before.grid.get(point) != Some(&after.grid[point]))
+        .collect()
+}
+
+// Rewrites every number from the mine layout, for boards imported from
+// somewhere whose numbers can't be trusted. Mines, walls and every cell's
+// state are left as they are.
+pub fn repair_adjacency(state: GameState) -> GameState {
+    let mut repaired = state.clone();
+    for point in state.grid.points() {
+        if let CellType::Empty { .. } = state.grid[point].cell_type {
+            repaired.grid[point].cell_type = CellType::Empty {
+                adjacent_mines: compute_cell_adjacency(&state, point),
+            };
+        }
+    }
+    repaired
+}
@@ -0,0 +1,60 @@
+use crate::{initial_state_seeded, open_cell, CellState, GameState, GameStatus, Point};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub const SAFE_REWARD: f32 = 1.0;
+pub const WASTED_REWARD: f32 = -0.1;
+pub const MINE_REWARD: f32 = -10.0;
+pub const WIN_REWARD: f32 = 10.0;
+
+// Plays one board after another, each step opening a single cell
+pub struct Env {
+    width: usize,
+    height: usize,
+    mines: usize,
+    rng: StdRng,
+    state: GameState,
+}
+
+impl Env {
+    pub fn new(width: usize, height: usize, mines: usize, seed: u64) -> Env {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let state = initial_state_seeded(width, height, mines, rng.gen());
+        Env {
+            width,
+            height,
+            mines,
+            rng,
+            state,
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn reset(&mut self) -> Vec<Vec<i8>> {
+        self.state = initial_state_seeded(self.width, self.height, self.mines, self.rng.gen());
+        self.state.observation()
+    }
+
+    // Opening new safe cells is rewarded, clicking an opened cell is a wasted
+    // step, and a mine or a win ends the episode
+    pub fn step(&mut self, action: Point) -> (Vec<Vec<i8>>, f32, bool) {
+        if !matches!(self.state.status, GameStatus::InProgress) {
+            return (self.state.observation(), 0.0, true);
+        }
+
+        let wasted = matches!(self.state.grid[action].state, CellState::Opened);
+        let state = std::mem::replace(&mut self.state, GameState::empty(0, 0));
+        self.state = open_cell(state, action);
+
+        let (reward, done) = match self.state.status {
+            GameStatus::Won => (WIN_REWARD, true),
+            GameStatus::Lost => (MINE_REWARD, true),
+            GameStatus::InProgress if wasted => (WASTED_REWARD, false),
+            GameStatus::InProgress => (SAFE_REWARD, false),
+        };
+        (self.state.observation(), reward, done)
+    }
+}
@@ -0,0 +1,613 @@
+use crate::{
+    advance_forced, apply_move, best_guess, board_3bv, debug_validate, deduce_all, highlight_safe,
+    initial_state_seeded, opened_numbers, CellState, CellType, Difficulty, Flag, GameState,
+    GameStatus, Move, Point,
+};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use std::fmt;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Where a game came from, for save files and results screens
+#[derive(Debug, Clone)]
+pub struct GameMeta {
+    pub seed: Option<u64>,
+    pub created_at: SystemTime,
+    pub difficulty: Difficulty,
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+}
+
+// Tracks the player's clicks and the game's provenance on top of the plain state
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub state: GameState,
+    pub meta: GameMeta,
+    // Plays every forced move after each player action
+    pub assist: bool,
+    // A cascade counts as the one click that started it
+    open_clicks: u32,
+    chord_clicks: u32,
+    // Set by the action that ends the game
+    finished_at: Option<SystemTime>,
+    // Sure flags the player placed, and how many of them were on mines
+    flags_placed: u32,
+    flags_on_mines: u32,
+    // Opened a cell while the solver had no safe cell to offer
+    forced_guess: bool,
+    // Moves a mine out from under the first click instead of losing on it
+    pub safe_first_click: bool,
+    pub(crate) rng: GameRng,
+    // Where the game was opened first, for retries that skip the opening
+    first_open: Option<Point>,
+    on_move: MoveHook,
+}
+
+pub type OnMove = Box<dyn FnMut(&Move, &GameState) + Send + Sync>;
+
+// Holds the on_move callback so Game keeps its derives. A closure can't be
+// cloned, so a cloned game starts without one.
+#[derive(Default)]
+struct MoveHook(Option<OnMove>);
+
+impl Clone for MoveHook {
+    fn clone(&self) -> MoveHook {
+        MoveHook(None)
+    }
+}
+
+impl fmt::Debug for MoveHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+// The RNG a game draws relocations from, along with what rebuilds it: the
+// seed and how many 32 bit words were drawn. StdRng can't be inspected, but
+// it gives every draw out of whole words, so skipping that many words from
+// the seed lands on the same state.
+#[derive(Debug, Clone)]
+pub(crate) struct GameRng {
+    pub(crate) seed: u64,
+    pub(crate) words: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    fn new(seed: u64) -> GameRng {
+        GameRng::restore(seed, 0)
+    }
+
+    pub(crate) fn restore(seed: u64, words: u64) -> GameRng {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..words {
+            rng.next_u32();
+        }
+        GameRng { seed, words, rng }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.words += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.words += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.words += dest.len().div_ceil(4) as u64;
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.words += dest.len().div_ceil(4) as u64;
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Generation and simulation hand states across threads, so no field may make
+// them lose these bounds
+fn _assert_send_sync()
+where
+    GameState: Send + Sync,
+    Game: Send + Sync,
+{
+}
+
+// A fresh board of the same size and mask as the state with the mines laid
+fn same_play_area(state: &GameState, mines: &[Point]) -> GameState {
+    let grid = &state.grid;
+    GameState::from_mines_masked(grid.width(), grid.height(), mines, grid.mask().cloned())
+        .expect("mines off the walls of the board")
+}
+
+impl Game {
+    // A hand-built board has no seed and counts as a custom difficulty
+    pub fn new(state: GameState) -> Game {
+        let width = state.grid.width();
+        let height = state.grid.height();
+        let mines = state
+            .grid
+            .iter()
+            .filter(|cell| matches!(cell.cell_type, CellType::Mine))
+            .count();
+        Game {
+            state,
+            meta: GameMeta {
+                seed: None,
+                created_at: SystemTime::now(),
+                difficulty: Difficulty::Custom {
+                    width,
+                    height,
+                    mines,
+                },
+                width,
+                height,
+                mines,
+            },
+            assist: false,
+            open_clicks: 0,
+            chord_clicks: 0,
+            finished_at: None,
+            flags_placed: 0,
+            flags_on_mines: 0,
+            forced_guess: false,
+            safe_first_click: false,
+            rng: GameRng::new(rand::thread_rng().gen()),
+            first_open: None,
+            on_move: MoveHook::default(),
+        }
+    }
+
+    // Without a seed a random one is drawn, so the board can always be regenerated
+    pub fn from_difficulty(difficulty: Difficulty, seed: Option<u64>) -> Game {
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let (width, height, mines) = difficulty.params();
+        Game {
+            state: initial_state_seeded(width, height, mines, seed),
+            meta: GameMeta {
+                seed: Some(seed),
+                created_at: SystemTime::now(),
+                difficulty,
+                width,
+                height,
+                mines,
+            },
+            assist: false,
+            open_clicks: 0,
+            chord_clicks: 0,
+            finished_at: None,
+            flags_placed: 0,
+            flags_on_mines: 0,
+            forced_guess: false,
+            safe_first_click: false,
+            // Relocations get their own stream so they don't repeat the layout's
+            rng: GameRng::new(seed ^ 0x9e37_79b9_7f4a_7c15),
+            first_open: None,
+            on_move: MoveHook::default(),
+        }
+    }
+
+    pub fn clicks(&self) -> u32 {
+        self.open_clicks + self.chord_clicks
+    }
+
+    pub fn open_clicks(&self) -> u32 {
+        self.open_clicks
+    }
+
+    pub fn chord_clicks(&self) -> u32 {
+        self.chord_clicks
+    }
+
+    // A fresh copy of the same board keeping only the flags placed so far
+    pub fn retry_keep_flags(&self) -> GameState {
+        // The layout is rebuilt from the current board rather than the seed, so
+        // it is the same board even for hand-built games
+        let mut retry = same_play_area(&self.state, &self.state.mine_positions());
+        for point in self.state.grid.points() {
+            if let CellState::Unopened(flag) = self.state.grid[point].state {
+                retry.grid[point].state = CellState::Unopened(flag);
+            }
+        }
+
+        // Every opened mine cost a life
+        let opened_mines = self
+            .state
+            .grid
+            .iter()
+            .filter(|cell| matches!(cell.cell_type, CellType::Mine))
+            .filter(|cell| matches!(cell.state, CellState::Opened))
+            .count() as u32;
+        GameState {
+            lives: self.state.lives + opened_mines,
+            rules: self.state.rules,
+            ..retry
+        }
+    }
+
+    // A new game on the same board with the first open already played, so
+    // the retry starts where the real decisions do. Seeded games are rebuilt
+    // from the seed, and the relocation RNG starts over, so a first click
+    // moved off a mine moves it the same way again. Options and rules carry
+    // over; click counts and timing start over.
+    pub fn retry_with_first_move(&self) -> Game {
+        let mut retry = match self.meta.seed {
+            Some(seed) => Game::from_difficulty(self.meta.difficulty, Some(seed)),
+            None => Game::new(same_play_area(&self.state, &self.state.mine_positions())),
+        };
+        let opened_mines = self
+            .state
+            .grid
+            .iter()
+            .filter(|cell| matches!(cell.cell_type, CellType::Mine))
+            .filter(|cell| matches!(cell.state, CellState::Opened))
+            .count() as u32;
+        retry.state.lives = self.state.lives + opened_mines;
+        retry.state.rules = self.state.rules;
+        retry.assist = self.assist;
+        retry.safe_first_click = self.safe_first_click;
+        retry.rng = GameRng::new(self.rng.seed);
+        if let Some(point) = self.first_open {
+            retry.open(point);
+        }
+        retry
+    }
+
+    pub fn open(&mut self, point: Point) {
+        if let GameStatus::InProgress = self.state.status {
+            self.open_clicks += 1;
+            self.first_open.get_or_insert(point);
+        }
+        // The first click is a guess on every board, so only later ones count
+        let started = self.state.grid.any_opened();
+        if started
+            && self.state.status == GameStatus::InProgress
+            && matches!(self.state.grid[point].state, CellState::Unopened(_))
+            && deduce_all(&self.state).0.is_empty()
+        {
+            self.forced_guess = true;
+        }
+        let cell = self.state.grid[point];
+        if self.safe_first_click
+            && !started
+            && self.state.status == GameStatus::InProgress
+            && cell.cell_type == CellType::Mine
+            && self.state.rules.open_flag_policy.opens(cell.state)
+        {
+            self.relocate_mine(point);
+        }
+        self.apply(Move::Open(point));
+    }
+
+    // Moves the mine to a closed safe cell drawn from the game's RNG, keeping
+    // every flag where it is. Nothing moves when there is no room.
+    fn relocate_mine(&mut self, from: Point) {
+        let grid = &self.state.grid;
+        let free = grid
+            .points()
+            .filter(|&point| {
+                point != from && grid[point].cell_type != CellType::Mine && !grid.is_wall(point)
+            })
+            .collect::<Vec<Point>>();
+        if free.is_empty() {
+            return;
+        }
+        let to = free[self.rng.gen_range(0..free.len())];
+        let mines = self
+            .state
+            .mine_positions()
+            .into_iter()
+            .map(|mine| if mine == from { to } else { mine })
+            .collect::<Vec<Point>>();
+
+        let mut moved = same_play_area(&self.state, &mines).grid;
+        for point in grid.points() {
+            moved[point].state = grid[point].state;
+        }
+        self.state.grid = moved;
+    }
+
+    pub fn chord(&mut self, point: Point) {
+        if let GameStatus::InProgress = self.state.status {
+            self.chord_clicks += 1;
+        }
+        self.apply(Move::Chord(point));
+    }
+
+    pub fn flag(&mut self, point: Point, flag: Flag) {
+        let cell = self.state.grid[point];
+        if self.state.status == GameStatus::InProgress
+            && flag == Flag::Sure
+            && matches!(
+                cell.state,
+                CellState::Unopened(Flag::Unflagged | Flag::Unsure)
+            )
+        {
+            self.flags_placed += 1;
+            if let CellType::Mine = cell.cell_type {
+                self.flags_on_mines += 1;
+            }
+        }
+        self.apply(Move::Flag(point, flag));
+    }
+
+    // Plays a recorded move the way the player made it
+    pub fn play(&mut self, action: Move) {
+        match action {
+            Move::Open(point) => self.open(point),
+            Move::Flag(point, flag) => self.flag(point, flag),
+            Move::Chord(point) => self.chord(point),
+        }
+    }
+
+    // 3BV per click on a won game, 1.0 being a perfect clear without chording
+    pub fn efficiency(&self) -> Option<f64> {
+        match self.state.status {
+            GameStatus::Won if self.clicks() > 0 => {
+                Some(f64::from(board_3bv(&self.state)) / f64::from(self.clicks()))
+            }
+            _ => None,
+        }
+    }
+
+    // Called after every open, flag and chord with the move and the state it
+    // left, forced moves included. Only moves made on this Game reach it, so
+    // replays, which rebuild plain states, never do.
+    pub fn set_on_move(&mut self, on_move: Option<OnMove>) {
+        self.on_move = MoveHook(on_move);
+    }
+
+    fn apply(&mut self, action: Move) {
+        let state = std::mem::replace(&mut self.state, GameState::empty(0, 0));
+        let state = apply_move(state, action);
+        self.state = if self.assist {
+            advance_forced(state)
+        } else {
+            state
+        };
+        if self.finished_at.is_none() && self.state.status != GameStatus::InProgress {
+            self.finished_at = Some(SystemTime::now());
+        }
+        if let Some(on_move) = &mut self.on_move.0 {
+            on_move(&action, &self.state);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameReport {
+    pub status: GameStatus,
+    // Until the game ended, or until now for a game still in progress
+    pub elapsed: Duration,
+    pub clicks: u32,
+    pub open_clicks: u32,
+    pub chord_clicks: u32,
+    pub board_3bv: u32,
+    pub efficiency: Option<f64>,
+    // Share of the Sure flags the player placed that were on mines
+    pub flag_accuracy: Option<f64>,
+    pub forced_guess: bool,
+}
+
+impl fmt::Display for GameReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |value: Option<f64>| match value {
+            Some(value) => format!("{:.0}%", value * 100.0),
+            None => "-".to_string(),
+        };
+        writeln!(f, "{:?} in {:.1}s", self.status, self.elapsed.as_secs_f64())?;
+        writeln!(
+            f,
+            "clicks: {} ({} open, {} chord), 3BV: {}, efficiency: {}",
+            self.clicks,
+            self.open_clicks,
+            self.chord_clicks,
+            self.board_3bv,
+            percent(self.efficiency)
+        )?;
+        writeln!(f, "flag accuracy: {}", percent(self.flag_accuracy))?;
+        writeln!(
+            f,
+            "forced guess: {}",
+            if self.forced_guess { "yes" } else { "no" }
+        )
+    }
+}
+
+pub fn game_report(game: &Game) -> GameReport {
+    let finished_at = game.finished_at.unwrap_or_else(SystemTime::now);
+    GameReport {
+        status: game.state.status,
+        elapsed: finished_at
+            .duration_since(game.meta.created_at)
+            .unwrap_or_default(),
+        clicks: game.clicks(),
+        open_clicks: game.open_clicks(),
+        chord_clicks: game.chord_clicks(),
+        board_3bv: board_3bv(&game.state),
+        efficiency: game.efficiency(),
+        flag_accuracy: match game.flags_placed {
+            0 => None,
+            placed => Some(f64::from(game.flags_on_mines) / f64::from(placed)),
+        },
+        forced_guess: game.forced_guess,
+    }
+}
+
+// Plays the game out with the solver, clicking like a player would so the
+// game's click counts measure the solver. Proven mines are flagged, a number
+// whose closed neighbors are all proven is chorded when that opens more than
+// one cell, and proven safe cells are opened one click each. With nothing
+// proven it opens the cell least likely to be a mine, starting with
+// first_click on a fresh board.
+pub fn auto_play(game: &mut Game, first_click: Point) {
+    auto_play_with_rng(game, first_click, &mut rand::thread_rng());
+}
+
+// Same as auto_play, with the rng choosing between equally good guesses, so a
+// seeded rng plays a board the same way every time
+pub fn auto_play_with_rng<R: Rng + ?Sized>(game: &mut Game, first_click: Point, rng: &mut R) {
+    if !game.state.grid.any_opened() {
+        game.open(first_click);
+    }
+
+    while let GameStatus::InProgress = game.state.status {
+        debug_assert_eq!(debug_validate(&game.state), Ok(()));
+        let (safe, mines) = deduce_all(&game.state);
+        for &point in mines.iter() {
+            if game.state.grid[point].state != CellState::Unopened(Flag::Sure) {
+                game.flag(point, Flag::Sure);
+            }
+        }
+
+        let chordable = opened_numbers(&game.state)
+            .into_iter()
+            .map(|(point, _)| (point, highlight_safe(&game.state, point)))
+            .filter(|(_, opens)| opens.len() > 1 && opens.iter().all(|point| safe.contains(point)))
+            .max_by_key(|(_, opens)| opens.len());
+        if let Some((point, _)) = chordable {
+            game.chord(point);
+            continue;
+        }
+
+        let policy = game.state.rules.open_flag_policy;
+        let closed = |point: &Point| policy.opens(game.state.grid[*point].state);
+        let next = match game
+            .state
+            .grid
+            .points()
+            .filter(closed)
+            .find(|point| safe.contains(point))
+        {
+            Some(point) => point,
+            None => match best_guess(&game.state, rng) {
+                Some((point, _)) => point,
+                None => break,
+            },
+        };
+        game.open(next);
+    }
+}
+
+// How simulate plays each game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    // auto_play from the center cell
+    #[default]
+    Solver,
+    // Opens closed cells at random, as a baseline for the solver
+    Random,
+}
+
+// Tallies of a batch of simulated games
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationResult {
+    pub games: usize,
+    pub wins: usize,
+    pub clicks: u64,
+}
+
+impl SimulationResult {
+    pub fn win_rate(&self) -> Option<f64> {
+        match self.games {
+            0 => None,
+            games => Some(self.wins as f64 / games as f64),
+        }
+    }
+
+    fn add(self, other: SimulationResult) -> SimulationResult {
+        SimulationResult {
+            games: self.games + other.games,
+            wins: self.wins + other.wins,
+            clicks: self.clicks + other.clicks,
+        }
+    }
+}
+
+// Plays the games seeded seed, seed + 1 and so on to the end. Game i draws
+// its board and its guesses from seed + i alone, so every game plays the
+// same however the batch is split up.
+pub fn simulate(
+    difficulty: Difficulty,
+    games: usize,
+    seed: u64,
+    strategy: Strategy,
+) -> SimulationResult {
+    (0..games)
+        .map(|index| simulate_one(difficulty, seed.wrapping_add(index as u64), strategy))
+        .fold(SimulationResult::default(), SimulationResult::add)
+}
+
+// Same as simulate with the games spread over up to threads threads. The
+// tallies are sums, so the result is exactly simulate's.
+pub fn simulate_parallel(
+    difficulty: Difficulty,
+    games: usize,
+    seed: u64,
+    threads: usize,
+    strategy: Strategy,
+) -> SimulationResult {
+    let threads = threads.clamp(1, games.max(1));
+    // Worker w takes games w, w + threads and so on, like solve_components
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|worker| {
+                scope.spawn(move || {
+                    (worker..games)
+                        .step_by(threads)
+                        .map(|index| {
+                            simulate_one(difficulty, seed.wrapping_add(index as u64), strategy)
+                        })
+                        .fold(SimulationResult::default(), SimulationResult::add)
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("simulation thread panicked"))
+            .fold(SimulationResult::default(), SimulationResult::add)
+    })
+}
+
+fn simulate_one(difficulty: Difficulty, seed: u64, strategy: Strategy) -> SimulationResult {
+    let mut game = Game::from_difficulty(difficulty, Some(seed));
+    // Guesses get their own stream, as the layout's would make them follow
+    // the mines
+    let mut rng = StdRng::seed_from_u64(seed ^ 0x5851_f42d_4c95_7f2d);
+    match strategy {
+        Strategy::Solver => {
+            let center = (game.meta.width / 2, game.meta.height / 2);
+            auto_play_with_rng(&mut game, center, &mut rng);
+        }
+        Strategy::Random => {
+            while game.state.status == GameStatus::InProgress {
+                let closed = game
+                    .state
+                    .grid
+                    .points()
+                    .filter(|&point| game.state.grid[point].state != CellState::Opened)
+                    .collect::<Vec<Point>>();
+                match closed.choose(&mut rng) {
+                    Some(&point) => game.open(point),
+                    None => break,
+                }
+            }
+        }
+    }
+    SimulationResult {
+        games: 1,
+        wins: usize::from(game.state.status == GameStatus::Won),
+        clicks: u64::from(game.clicks()),
+    }
+}
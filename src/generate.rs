@@ -0,0 +1,810 @@
+use crate::{
+    check_mask, find_adjacent, is_solvable_without_guessing, open_cell, validate_params, Cell,
+    CellState, CellType, Difficulty, Flag, GameState, GameStatus, MineError, Point,
+};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cmp::min;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+// Mine positions as the first cells of a Fisher-Yates shuffle of every cell in
+// row order, so generation is O(cells) at any density and the layout is a pure
+// function of the RNG sequence
+pub fn generate_layout_shuffle<R: Rng>(
+    width: usize,
+    height: usize,
+    mines: usize,
+    rng: &mut R,
+) -> Vec<Point> {
+    random_coordinates(
+        mines,
+        width,
+        height,
+        &HashSet::new(),
+        MineDistribution::Uniform,
+        rng,
+    )
+}
+
+// A shuffle that is the same for the same seed and length, for MinePlacers
+// and tests that must come out the same on every run. It runs on StdRng,
+// whose stream rand only keeps the same within a version.
+pub fn seeded_shuffle<T>(items: &mut [T], seed: u64) {
+    items.shuffle(&mut StdRng::seed_from_u64(seed));
+}
+
+// Same as generate_layout_shuffle with mines biased by the distribution
+pub fn generate_layout_weighted<R: Rng>(
+    width: usize,
+    height: usize,
+    mines: usize,
+    distribution: MineDistribution,
+    rng: &mut R,
+) -> Vec<Point> {
+    random_coordinates(mines, width, height, &HashSet::new(), distribution, rng)
+}
+
+// Mines laid with chances proportional to the weights, indexed [y][x]. Cells
+// of weight 0 never get a mine, so there must be at least as many cells of
+// positive weight as mines.
+pub fn initial_state_weighted<R: Rng>(
+    width: usize,
+    height: usize,
+    mines: usize,
+    weights: &[Vec<f64>],
+    rng: &mut R,
+) -> Result<GameState, MineError> {
+    validate_params(width, height, mines)?;
+    if weights.len() != height || weights.iter().any(|row| row.len() != width) {
+        return Err(MineError::InvalidWeights(format!(
+            "weights must be {} rows of {}",
+            height, width
+        )));
+    }
+    let mut weighted = Vec::new();
+    for (y, row) in weights.iter().enumerate() {
+        for (x, &weight) in row.iter().enumerate() {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(MineError::InvalidWeights(format!(
+                    "({}, {}) has weight {}",
+                    x, y, weight
+                )));
+            }
+            if weight > 0.0 {
+                weighted.push(((x, y), weight));
+            }
+        }
+    }
+    if weighted.len() < mines {
+        return Err(MineError::InvalidWeights(format!(
+            "only {} cells can take the {} mines",
+            weighted.len(),
+            mines
+        )));
+    }
+
+    let layout = weighted_sample(weighted, mines, rng);
+    Ok(GameState::from_mines(width, height, &layout))
+}
+
+// Mines laid uniformly over the play area of the mask, see
+// GameState::from_mines_masked. Walls take no mines, so the mines must leave
+// at least one playable cell safe.
+pub fn initial_state_masked<R: Rng>(
+    width: usize,
+    height: usize,
+    mines: usize,
+    mask: Option<Vec<Vec<bool>>>,
+    rng: &mut R,
+) -> Result<GameState, MineError> {
+    validate_params(width, height, mines)?;
+    check_mask(width, height, mask.as_ref())?;
+    let mut playable = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| mask.as_ref().is_none_or(|mask| mask[y][x]))
+        .collect::<Vec<Point>>();
+    if mines >= playable.len() {
+        return Err(MineError::TooManyMines {
+            mines,
+            cells: playable.len(),
+        });
+    }
+
+    let (layout, _) = playable.partial_shuffle(rng, mines);
+    GameState::from_mines_masked(width, height, layout, mask)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MineDistribution {
+    // Every cell equally likely
+    #[default]
+    Uniform,
+    // Weight falls off linearly from the border toward the middle
+    EdgeHeavy,
+    // Weight grows linearly from the border toward the middle
+    CenterHeavy,
+}
+
+impl MineDistribution {
+    // A cell's depth is how many rings in from the border it is, 0 on the
+    // border. EdgeHeavy weighs a cell 1 + (deepest - depth) and CenterHeavy
+    // 1 + depth, so the favored ring is deepest + 1 times as likely to get
+    // a mine as the other extreme.
+    fn weight(&self, point: Point, width: usize, height: usize) -> f64 {
+        let depth = |(x, y): Point| min(min(x, width - 1 - x), min(y, height - 1 - y));
+        let deepest = depth(((width - 1) / 2, (height - 1) / 2));
+        match self {
+            MineDistribution::Uniform => 1.0,
+            MineDistribution::EdgeHeavy => (1 + deepest - depth(point)) as f64,
+            MineDistribution::CenterHeavy => (1 + depth(point)) as f64,
+        }
+    }
+}
+
+fn random_coordinates<R: Rng + ?Sized>(
+    count: usize,
+    max_width: usize,
+    max_height: usize,
+    excluded: &HashSet<Point>,
+    distribution: MineDistribution,
+    rng: &mut R,
+) -> Vec<Point> {
+    let mut coordinates = (0..max_height)
+        .flat_map(|y| (0..max_width).map(move |x| (x, y)))
+        .filter(|point| !excluded.contains(point))
+        .collect::<Vec<Point>>();
+    if let MineDistribution::Uniform = distribution {
+        let (chosen, _) = coordinates.partial_shuffle(rng, count);
+        return chosen.to_vec();
+    }
+
+    let weighted = coordinates
+        .into_iter()
+        .map(|point| (point, distribution.weight(point, max_width, max_height)))
+        .collect();
+    weighted_sample(weighted, count, rng)
+}
+
+// Weighted sampling without replacement: every cell draws the key
+// u^(1 / weight) and the largest keys win. Weights must be positive.
+fn weighted_sample<R: Rng + ?Sized>(
+    weighted: Vec<(Point, f64)>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Point> {
+    let mut keyed = weighted
+        .into_iter()
+        .map(|(point, weight)| (rng.gen::<f64>().powf(1.0 / weight), point))
+        .collect::<Vec<(f64, Point)>>();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    keyed
+        .into_iter()
+        .take(count)
+        .map(|(_, point)| point)
+        .collect()
+}
+
+// A way of laying out mines, for boards made with initial_state_with_placer.
+// A placer given the first click keeps it clear of mines, and the few that
+// can't always lay out the mines asked for return an error instead.
+pub trait MinePlacer {
+    fn place(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: Option<Point>,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<Point>, MineError>;
+}
+
+impl MinePlacer for MineDistribution {
+    fn place(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: Option<Point>,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<Point>, MineError> {
+        validate_params(width, height, mines)?;
+        let excluded = first_click.into_iter().collect::<HashSet<Point>>();
+        let free = width * height - excluded.len();
+        if mines > free {
+            return Err(MineError::TooManyMines { mines, cells: free });
+        }
+        Ok(random_coordinates(
+            mines, width, height, &excluded, *self, rng,
+        ))
+    }
+}
+
+// Boards with no guess needed after the first click, retried like
+// generate_no_guess. Without a first click the center cell is taken as one.
+#[derive(Debug, Clone, Copy)]
+pub struct NoGuess {
+    pub distribution: MineDistribution,
+    pub max_attempts: usize,
+}
+
+impl MinePlacer for NoGuess {
+    fn place(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: Option<Point>,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<Point>, MineError> {
+        validate_params(width, height, mines)?;
+        let first_click = first_click.unwrap_or(((width - 1) / 2, (height - 1) / 2));
+        let params = GenerationParams {
+            width,
+            height,
+            mines,
+            first_click,
+            seed: None,
+            max_attempts: self.max_attempts,
+            distribution: self.distribution,
+            safe_zone: None,
+            safe_zone_neighbors: false,
+        };
+        let state = no_guess_layout(&params, rng, &mut |_| (), &AtomicBool::new(false))?;
+        Ok(state.mine_positions())
+    }
+}
+
+// Checks the board and whatever the placer hands back before building on it
+pub fn initial_state_with_placer(
+    width: usize,
+    height: usize,
+    mines: usize,
+    first_click: Option<Point>,
+    placer: &dyn MinePlacer,
+    rng: &mut dyn RngCore,
+) -> Result<GameState, MineError> {
+    validate_params(width, height, mines)?;
+    let empty = GameState::empty(width, height);
+    if let Some(point) = first_click.filter(|&point| !empty.grid.in_bounds(point)) {
+        return Err(MineError::OutOfBounds(point));
+    }
+
+    let mut layout = placer.place(width, height, mines, first_click, rng)?;
+    if let Some(&point) = layout.iter().find(|&&point| !empty.grid.in_bounds(point)) {
+        return Err(MineError::OutOfBounds(point));
+    }
+    layout.sort_unstable_by_key(|&(x, y)| (y, x));
+    layout.dedup();
+    if layout.len() != mines {
+        return Err(MineError::InvalidPlacement {
+            expected: mines,
+            placed: layout.len(),
+        });
+    }
+    Ok(empty.place_mines(&layout))
+}
+
+pub fn initial_state(width: usize, height: usize, mines: usize) -> GameState {
+    initial_state_with_rng(width, height, mines, &mut rand::thread_rng())
+}
+
+pub fn initial_state_seeded(width: usize, height: usize, mines: usize, seed: u64) -> GameState {
+    initial_state_with_rng(width, height, mines, &mut StdRng::seed_from_u64(seed))
+}
+
+fn initial_state_with_rng<R: Rng>(
+    width: usize,
+    height: usize,
+    mines: usize,
+    rng: &mut R,
+) -> GameState {
+    // Nothing is excluded without a first click, so a uniform layout always fits
+    let mines = MineDistribution::Uniform
+        .place(width, height, mines, None, rng)
+        .expect("uniform layout without a first click");
+    GameState::from_mines(width, height, &mines)
+}
+
+// Boards for benchmarks and simulations, board i being the same as
+// initial_state_seeded with base_seed + i
+pub fn generate_many(difficulty: Difficulty, count: usize, base_seed: u64) -> Vec<GameState> {
+    let (width, height, mines) = difficulty.params();
+    let template = GameState::empty(width, height);
+    (0..count as u64)
+        .map(|index| {
+            let mut rng = StdRng::seed_from_u64(base_seed.wrapping_add(index));
+            let layout = generate_layout_shuffle(width, height, mines, &mut rng);
+            template.clone().place_mines(&layout)
+        })
+        .collect()
+}
+
+// A day on the Gregorian calendar, for boards of the day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CalendarDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl CalendarDate {
+    // Today in UTC, so players in every time zone agree on the date
+    pub fn today_utc() -> CalendarDate {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        CalendarDate::from_days_since_epoch(seconds.div_euclid(86_400))
+    }
+
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's algorithm
+    pub fn from_days_since_epoch(days: i64) -> CalendarDate {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        CalendarDate {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    // The date written as the decimal number YYYYMMDD, so 2024-03-09 is the
+    // seed 20240309
+    pub fn seed(&self) -> u64 {
+        (i64::from(self.year) * 10_000 + i64::from(self.month) * 100 + i64::from(self.day)) as u64
+    }
+}
+
+// The same board for everyone playing the difficulty on that date
+pub fn daily_board(date: CalendarDate, difficulty: Difficulty) -> GameState {
+    let (width, height, mines) = difficulty.params();
+    initial_state_seeded(width, height, mines, date.seed())
+}
+
+// The same board for everyone using the phrase, so "blue-whale-42" can be
+// shared by word of mouth. The seed is the 64 bit FNV-1a hash of the
+// phrase's UTF-8 bytes, which unlike std's hasher never changes between
+// Rust versions.
+pub fn initial_state_from_phrase(phrase: &str, difficulty: Difficulty) -> GameState {
+    let seed = phrase
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let (width, height, mines) = difficulty.params();
+    initial_state_seeded(width, height, mines, seed)
+}
+
+// A shared puzzle with some cells opened as a starting hint. Each of them
+// opens like a click, cascading from zeros, and none may be a mine. Every
+// mine is listed once, so the count checked is the count laid.
+pub fn initial_state_with_openings(
+    width: usize,
+    height: usize,
+    mines: &[Point],
+    pre_opened: &[Point],
+) -> Result<GameState, MineError> {
+    let mut seen = HashSet::new();
+    if let Some(&point) = mines.iter().find(|&&point| !seen.insert(point)) {
+        return Err(MineError::DuplicateMine(point));
+    }
+    validate_params(width, height, mines.len())?;
+    let mut state = GameState::empty(width, height);
+    if let Some(&point) = mines
+        .iter()
+        .chain(pre_opened)
+        .find(|&&point| !state.grid.in_bounds(point))
+    {
+        return Err(MineError::OutOfBounds(point));
+    }
+
+    state = GameState::from_mines(width, height, mines);
+    for &point in pre_opened {
+        if let CellType::Mine = state.grid[point].cell_type {
+            return Err(MineError::PreOpenedMine(point));
+        }
+        state = open_cell(state, point);
+    }
+    Ok(state)
+}
+
+// A board for huge, mostly empty layouts. Only the mines, opened cells and
+// flags are stored, and numbers are counted from the mines when asked for.
+// A cascade still stores every cell it opens, so a click into a wide empty
+// area costs as much as it reveals.
+//
+// It shares no trait with GameState and only covers plain play: open_cell and
+// change_flag under the default rules with the lives set here, and the cell,
+// neighbor and count queries below. There is no chording, no Rules, and none
+// of the solver, rendering or save functions take it.
+#[derive(Debug, Clone)]
+pub struct SparseState {
+    pub status: GameStatus,
+    pub lives: u32,
+    width: usize,
+    height: usize,
+    mines: HashSet<Point>,
+    opened: HashSet<Point>,
+    flags: HashMap<Point, Flag>,
+    safe_opened: usize,
+}
+
+// Same as initial_state_seeded, picking the mines without ever listing every
+// cell of the board
+pub fn initial_state_sparse(
+    width: usize,
+    height: usize,
+    mines: usize,
+    seed: u64,
+) -> Result<SparseState, MineError> {
+    validate_params(width, height, mines)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Floyd's sampling of distinct cell indexes, one draw per mine
+    let cells = width * height;
+    let mut chosen = HashSet::with_capacity(mines);
+    for upper in cells - mines..cells {
+        let index = rng.gen_range(0..=upper);
+        if !chosen.insert(index) {
+            chosen.insert(upper);
+        }
+    }
+    let layout = chosen
+        .into_iter()
+        .map(|index| (index % width, index / width))
+        .collect::<Vec<Point>>();
+    Ok(SparseState::from_mines(width, height, &layout))
+}
+
+impl SparseState {
+    pub fn from_mines(width: usize, height: usize, mines: &[Point]) -> SparseState {
+        SparseState {
+            status: GameStatus::InProgress,
+            lives: 1,
+            width,
+            height,
+            mines: mines.iter().copied().collect(),
+            opened: HashSet::new(),
+            flags: HashMap::new(),
+            safe_opened: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn in_bounds(&self, point: Point) -> bool {
+        let (x, y) = point;
+        x < self.width && y < self.height
+    }
+
+    pub fn adjacent(&self, point: Point) -> Vec<Point> {
+        find_adjacent(point.0, point.1, self.width, self.height)
+    }
+
+    pub fn adjacent_mines(&self, point: Point) -> u8 {
+        self.adjacent(point)
+            .into_iter()
+            .filter(|adj| self.mines.contains(adj))
+            .count() as u8
+    }
+
+    // The cell as a dense GameState would store it
+    pub fn cell(&self, point: Point) -> Cell {
+        Cell {
+            cell_type: if self.mines.contains(&point) {
+                CellType::Mine
+            } else {
+                CellType::Empty {
+                    adjacent_mines: self.adjacent_mines(point),
+                }
+            },
+            state: if self.opened.contains(&point) {
+                CellState::Opened
+            } else {
+                CellState::Unopened(self.flags.get(&point).copied().unwrap_or(Flag::Unflagged))
+            },
+        }
+    }
+
+    pub fn mine_positions(&self) -> Vec<Point> {
+        let mut mines = self.mines.iter().copied().collect::<Vec<Point>>();
+        mines.sort_by_key(|&(x, y)| (y, x));
+        mines
+    }
+
+    pub fn safe_cells_remaining(&self) -> usize {
+        self.width * self.height - self.mines.len() - self.safe_opened
+    }
+
+    // Same rules as open_cell with the default rules, so a Sure flag can't
+    // be opened
+    pub fn open_cell(self, point: Point) -> SparseState {
+        let mut state = self;
+        if state.status != GameStatus::InProgress
+            || state.opened.contains(&point)
+            || state.flags.get(&point) == Some(&Flag::Sure)
+        {
+            return state;
+        }
+
+        state.opened.insert(point);
+        state.flags.remove(&point);
+        if state.mines.contains(&point) {
+            state.lives = state.lives.saturating_sub(1);
+            if state.lives == 0 {
+                state.status = GameStatus::Lost;
+            }
+            return state;
+        }
+
+        state.safe_opened += 1;
+        let mut pending = VecDeque::from([point]);
+        while let Some(next) = pending.pop_front() {
+            if state.adjacent_mines(next) > 0 {
+                continue;
+            }
+            for adj in state.adjacent(next) {
+                let flagged = state
+                    .flags
+                    .get(&adj)
+                    .is_some_and(|&flag| flag != Flag::Unflagged);
+                if !flagged && state.opened.insert(adj) {
+                    state.safe_opened += 1;
+                    pending.push_back(adj);
+                }
+            }
+        }
+        // The default rules leave the flags as the player put them on a win
+        if state.safe_cells_remaining() == 0 {
+            state.status = GameStatus::Won;
+        }
+        state
+    }
+
+    pub fn change_flag(self, point: Point, flag: Flag) -> SparseState {
+        let mut state = self;
+        if state.status == GameStatus::InProgress && !state.opened.contains(&point) {
+            match flag {
+                Flag::Unflagged => state.flags.remove(&point),
+                flag => state.flags.insert(point, flag),
+            };
+        }
+        state
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Symmetry {
+    // Top half mirrors the bottom half
+    Horizontal,
+    // Left half mirrors the right half
+    Vertical,
+    // Unchanged by a half turn around the center
+    Rotational,
+}
+
+impl Symmetry {
+    pub fn mirror(&self, point: Point, width: usize, height: usize) -> Point {
+        let (x, y) = point;
+        match self {
+            Symmetry::Horizontal => (x, height - 1 - y),
+            Symmetry::Vertical => (width - 1 - x, y),
+            Symmetry::Rotational => (width - 1 - x, height - 1 - y),
+        }
+    }
+}
+
+// Mines are placed in mirrored pairs. Cells on the axis of an odd dimension are
+// their own mirror and take the odd mine, if any. A first click keeps its
+// mirror clear too.
+impl MinePlacer for Symmetry {
+    fn place(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: Option<Point>,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<Point>, MineError> {
+        let mut singles = Vec::new();
+        let mut pairs = Vec::new();
+        for point in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+            let mirror = self.mirror(point, width, height);
+            if first_click.is_some_and(|click| click == point || click == mirror) {
+                continue;
+            }
+            match point.cmp(&mirror) {
+                std::cmp::Ordering::Equal => singles.push(point),
+                std::cmp::Ordering::Less => pairs.push((point, mirror)),
+                std::cmp::Ordering::Greater => (),
+            }
+        }
+
+        // As few self-mirrored mines as the pairs allow
+        let single_count = if mines / 2 <= pairs.len() {
+            mines % 2
+        } else {
+            mines - 2 * pairs.len()
+        };
+        if single_count > singles.len() {
+            return Err(MineError::NoSymmetricLayout { mines });
+        }
+
+        singles.shuffle(rng);
+        pairs.shuffle(rng);
+        Ok(singles
+            .into_iter()
+            .take(single_count)
+            .chain(
+                pairs
+                    .into_iter()
+                    .take((mines - single_count) / 2)
+                    .flat_map(|(point, mirror)| [point, mirror]),
+            )
+            .collect())
+    }
+}
+
+pub fn initial_state_symmetric(
+    width: usize,
+    height: usize,
+    mines: usize,
+    axis: Symmetry,
+) -> Result<GameState, MineError> {
+    initial_state_with_placer(width, height, mines, None, &axis, &mut rand::thread_rng())
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerationParams {
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    pub first_click: Point,
+    pub seed: Option<u64>,
+    pub max_attempts: usize,
+    pub distribution: MineDistribution,
+    // Cells kept clear of mines on top of the first click's, like the top
+    // row of a training board, along with their neighbors if asked
+    pub safe_zone: Option<Vec<Point>>,
+    pub safe_zone_neighbors: bool,
+}
+
+pub fn generate_no_guess(params: &GenerationParams) -> Result<GameState, MineError> {
+    generate_no_guess_with_progress(params, |_| (), &AtomicBool::new(false))
+}
+
+// Calls progress with the number of layouts tried so far, and gives up as soon
+// as cancel is set
+pub fn generate_no_guess_with_progress(
+    params: &GenerationParams,
+    mut progress: impl FnMut(usize),
+    cancel: &AtomicBool,
+) -> Result<GameState, MineError> {
+    let GenerationParams {
+        width,
+        height,
+        mines,
+        first_click,
+        ..
+    } = *params;
+    validate_params(width, height, mines)?;
+    let grid = GameState::empty(width, height).grid;
+    if !grid.in_bounds(first_click) {
+        return Err(MineError::OutOfBounds(first_click));
+    }
+    let safe_zone = params.safe_zone.as_deref().unwrap_or_default();
+    if let Some(&point) = safe_zone.iter().find(|&&point| !grid.in_bounds(point)) {
+        return Err(MineError::OutOfBounds(point));
+    }
+
+    let mut rng = match params.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    no_guess_layout(params, &mut rng, &mut progress, cancel)
+}
+
+// The retry loop of generate_no_guess, the seed in params being unused
+fn no_guess_layout(
+    params: &GenerationParams,
+    rng: &mut dyn RngCore,
+    progress: &mut dyn FnMut(usize),
+    cancel: &AtomicBool,
+) -> Result<GameState, MineError> {
+    let GenerationParams {
+        width,
+        height,
+        mines,
+        first_click,
+        max_attempts,
+        distribution,
+        ..
+    } = *params;
+    // Keep the first click and its neighbors clear so it opens a zero
+    let mut excluded = find_adjacent(first_click.0, first_click.1, width, height)
+        .into_iter()
+        .collect::<HashSet<Point>>();
+    excluded.insert(first_click);
+    for &point in params.safe_zone.iter().flatten() {
+        excluded.insert(point);
+        if params.safe_zone_neighbors {
+            excluded.extend(find_adjacent(point.0, point.1, width, height));
+        }
+    }
+    let free = width * height - excluded.len();
+    if mines > free {
+        return Err(MineError::TooManyMines { mines, cells: free });
+    }
+
+    for attempt in 1..=max_attempts {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(MineError::Cancelled);
+        }
+
+        let layout = random_coordinates(mines, width, height, &excluded, distribution, rng);
+        let state = GameState::from_mines(width, height, &layout);
+        progress(attempt);
+        if is_solvable_without_guessing(&state, first_click) {
+            return Ok(state);
+        }
+    }
+
+    Err(MineError::NoSolvableLayout {
+        attempts: max_attempts,
+    })
+}
+
+// Layouts tried per campaign level. Boards this sparse are solvable often
+// enough that running out means something is wrong.
+pub const CAMPAIGN_ATTEMPTS: usize = 10_000;
+
+// Boards for a campaign, growing from 8x8 at 10% mines to 24x16 at 18% over
+// the levels. Every board needs no guess after clicking its center cell, and
+// level i is generated from base_seed + i, so a campaign can be shared by its
+// seed alone. A level still unsolvable after CAMPAIGN_ATTEMPTS layouts fails
+// the campaign with NoSolvableLayout.
+pub fn campaign(levels: usize, base_seed: u64) -> Result<Vec<GameState>, MineError> {
+    (0..levels)
+        .map(|level| {
+            let t = match levels {
+                1 => 0.0,
+                _ => level as f64 / (levels - 1) as f64,
+            };
+            let width = 8 + (16.0 * t).round() as usize;
+            let height = 8 + (8.0 * t).round() as usize;
+            let density = 0.10 + 0.08 * t;
+            let params = GenerationParams {
+                width,
+                height,
+                mines: ((width * height) as f64 * density).round() as usize,
+                first_click: ((width - 1) / 2, (height - 1) / 2),
+                seed: Some(base_seed.wrapping_add(level as u64)),
+                max_attempts: CAMPAIGN_ATTEMPTS,
+                distribution: MineDistribution::Uniform,
+                safe_zone: None,
+                safe_zone_neighbors: false,
+            };
+            generate_no_guess(&params)
+        })
+        .collect()
+}
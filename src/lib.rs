@@ -0,0 +1,21 @@
+#[cfg(feature = "env")]
+pub mod env;
+
+mod board;
+mod game;
+mod generate;
+mod play;
+pub mod prelude;
+mod render;
+mod replay;
+mod save;
+mod solver;
+
+pub use board::*;
+pub use game::*;
+pub use generate::*;
+pub use play::*;
+pub use render::*;
+pub use replay::*;
+pub use save::*;
+pub use solver::*;
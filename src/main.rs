@@ -1,212 +1,149 @@
-use itertools::Itertools;
-use rand::distributions::{Distribution, Uniform};
-use std::cmp::min;
-use std::collections::HashSet;
-
-type Point = (usize, usize);
-
-#[derive(Debug, Clone, Copy)]
-enum Flag {
-    Unflagged,
-    Unsure,
-    Sure,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum CellType {
-    Empty { adjacent_mines: u8 },
-    Mine,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum CellState {
-    Opened,
-    Unopened(Flag),
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Cell {
-    cell_type: CellType,
-    state: CellState,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum GameStatus {
-    InProgress,
-    Lost,
-    Won,
+use rusty_mines::{
+    initial_state, initial_state_seeded, open_cell, validate_params, Difficulty, MineError,
+};
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage: rusty-mines [--difficulty beginner|intermediate|expert] \
+[--width N] [--height N] [--mines N] [--seed N]";
+
+#[derive(Debug)]
+struct Args {
+    width: usize,
+    height: usize,
+    mines: usize,
+    seed: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
-struct GameState {
-    status: GameStatus,
-    grid: Vec<Vec<Cell>>,
+#[derive(Debug)]
+enum ArgError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+    InvalidBoard(MineError),
 }
 
-fn random_coordinates(count: usize, max_width: usize, max_height: usize) -> Vec<Point> {
-    let mut rng = rand::thread_rng();
-    let width = Uniform::from(0..max_width);
-    let height = Uniform::from(0..max_height);
-
-    let mut coordinates = HashSet::with_capacity(count);
-    while coordinates.len() < count {
-        coordinates.insert((width.sample(&mut rng), height.sample(&mut rng)));
-    }
-
-    return coordinates.into_iter().collect();
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, ArgError> {
+    let value = value.ok_or_else(|| ArgError::MissingValue(flag.to_string()))?;
+    value
+        .parse()
+        .map_err(|_| ArgError::InvalidValue(flag.to_string(), value.clone()))
 }
 
-fn find_adjacent(x: usize, y: usize, width: usize, height: usize) -> Vec<Point> {
-    let xs = (if x == 0 { 0 } else { x - 1 }..min(x + 2, width)).collect::<Vec<usize>>();
-    let ys = (if y == 0 { 0 } else { y - 1 }..min(y + 2, height)).collect::<Vec<usize>>();
-
-    return xs
-        .into_iter()
-        .cartesian_product(ys.into_iter())
-        .into_iter()
-        .filter(|&(adj_x, adj_y)| x != adj_x || y != adj_y)
-        .collect();
-}
-
-fn initial_state(width: usize, height: usize, mines: usize) -> GameState {
-    let mut grid = vec![
-        vec![
-            Cell {
-                cell_type: CellType::Empty { adjacent_mines: 0 },
-                state: CellState::Unopened(Flag::Unflagged),
-            };
-            usize::from(width)
-        ];
-        usize::from(height)
-    ];
-
-    let mines = random_coordinates(mines, width, height);
-
-    // Inject mines
-    for &(mine_x, mine_y) in mines.iter() {
-        let mine = grid[mine_y][mine_x];
-        grid[mine_y][mine_x] = Cell {
-            cell_type: CellType::Mine,
-            ..mine
-        };
-
-        // Increment adjacent_mines count for adjacent cells
-        let adj = find_adjacent(mine_x, mine_y, width, height);
-        for &(x, y) in adj.iter() {
-            let cell = grid[y][x];
-            match cell.cell_type {
-                CellType::Empty { adjacent_mines } => {
-                    grid[y][x] = Cell {
-                        cell_type: CellType::Empty {
-                            adjacent_mines: adjacent_mines + 1,
-                        },
-                        ..cell
+fn parse_args(args: &[String]) -> Result<Args, ArgError> {
+    let mut difficulty = Difficulty::Beginner;
+    let mut width = None;
+    let mut height = None;
+    let mut mines = None;
+    let mut seed = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--difficulty" => {
+                difficulty = match args.next().map(|value| value.as_str()) {
+                    Some("beginner") => Difficulty::Beginner,
+                    Some("intermediate") => Difficulty::Intermediate,
+                    Some("expert") => Difficulty::Expert,
+                    Some(value) => {
+                        return Err(ArgError::InvalidValue(flag.clone(), value.to_string()))
                     }
+                    None => return Err(ArgError::MissingValue(flag.clone())),
                 }
-                CellType::Mine => (),
             }
+            "--width" => width = Some(parse_value(flag, args.next())?),
+            "--height" => height = Some(parse_value(flag, args.next())?),
+            "--mines" => mines = Some(parse_value(flag, args.next())?),
+            "--seed" => seed = Some(parse_value(flag, args.next())?),
+            _ => return Err(ArgError::UnknownFlag(flag.clone())),
         }
     }
 
-    return GameState {
-        grid,
-        status: GameStatus::InProgress,
+    // Anything not given explicitly comes from the chosen difficulty
+    let (default_width, default_height, default_mines) = difficulty.params();
+    let args = Args {
+        width: width.unwrap_or(default_width),
+        height: height.unwrap_or(default_height),
+        mines: mines.unwrap_or(default_mines),
+        seed,
     };
-}
+    validate_params(args.width, args.height, args.mines).map_err(ArgError::InvalidBoard)?;
 
-fn is_game_won(grid: &Vec<Vec<Cell>>) -> bool {
-    return grid.into_iter().flatten().all(|cell| match cell {
-        Cell {
-            cell_type: CellType::Empty { adjacent_mines: _ },
-            state: CellState::Opened,
-        } => true,
-        Cell {
-            cell_type: CellType::Empty { adjacent_mines: _ },
-            state: CellState::Unopened(_),
-        } => false,
-        Cell {
-            cell_type: CellType::Mine,
-            state: _,
-        } => true,
-    });
+    Ok(args)
 }
 
-fn open_cell(state: GameState, point: Point) -> GameState {
-    match state.status {
-        GameStatus::InProgress => {
-            let (x, y) = point;
-            let mut grid = state.grid;
-            let cell = grid[y][x];
-            match cell.state {
-                CellState::Opened => GameState {
-                    status: state.status,
-                    grid,
-                },
-                _ => {
-                    // TODO: Add algorithm to auto open empty cells without adjacent mines
-                    grid[y][x] = Cell {
-                        state: CellState::Opened,
-                        ..cell
-                    };
-                    match cell.cell_type {
-                        CellType::Mine => GameState {
-                            status: GameStatus::Lost,
-                            grid,
-                        },
-                        _ => GameState {
-                            status: if is_game_won(&grid) {
-                                GameStatus::Won
-                            } else {
-                                GameStatus::InProgress
-                            },
-                            grid,
-                        },
-                    }
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = match parse_args(&args) {
+        Ok(args) => args,
+        Err(err) => {
+            match err {
+                ArgError::UnknownFlag(flag) => eprintln!("unknown argument {}", flag),
+                ArgError::MissingValue(flag) => eprintln!("missing value for {}", flag),
+                ArgError::InvalidValue(flag, value) => {
+                    eprintln!("invalid value {} for {}", value, flag)
                 }
+                ArgError::InvalidBoard(err) => eprintln!("{}", err),
             }
+            eprintln!("{}", USAGE);
+            process::exit(2);
         }
-        _ => GameState {
-            status: state.status,
-            grid: state.grid,
-        },
-    }
+    };
+
+    let state = match args.seed {
+        Some(seed) => initial_state_seeded(args.width, args.height, args.mines, seed),
+        None => initial_state(args.width, args.height, args.mines),
+    };
+    let state = open_cell(state, (0, 0));
+    print!("{}", state.pretty());
 }
 
-fn change_flag(state: GameState, point: Point, flag: Flag) -> GameState {
-    match state.status {
-        GameStatus::InProgress => {
-            let (x, y) = point;
-            let mut grid = state.grid;
-            let cell = grid[y][x];
-            match cell.state {
-                CellState::Opened => GameState {
-                    status: state.status,
-                    grid,
-                },
-                _ => {
-                    grid[y][x] = Cell {
-                        state: CellState::Unopened(flag),
-                        ..cell
-                    };
-                    return GameState {
-                        status: if is_game_won(&grid) {
-                            GameStatus::Won
-                        } else {
-                            GameStatus::InProgress
-                        },
-                        grid,
-                    };
-                }
-            }
-        }
-        _ => GameState {
-            status: state.status,
-            grid: state.grid,
-        },
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, ArgError> {
+        parse_args(
+            &args
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>(),
+        )
     }
-}
 
-fn main() {
-    let state = initial_state(10, 10, 25);
-    open_cell(state, (0, 0));
+    #[test]
+    fn defaults_to_beginner() {
+        let args = parse("").unwrap();
+        assert_eq!((args.width, args.height, args.mines), (9, 9, 10));
+        assert_eq!(args.seed, None);
+    }
+
+    #[test]
+    fn explicit_values_override_the_difficulty() {
+        let args = parse("--difficulty expert --width 20 --mines 50 --seed 7").unwrap();
+        assert_eq!((args.width, args.height, args.mines), (20, 16, 50));
+        assert_eq!(args.seed, Some(7));
+    }
+
+    #[test]
+    fn bad_input_is_rejected() {
+        assert!(matches!(parse("--size 3"), Err(ArgError::UnknownFlag(_))));
+        assert!(matches!(parse("--width"), Err(ArgError::MissingValue(_))));
+        assert!(matches!(
+            parse("--width wide"),
+            Err(ArgError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            parse("--difficulty hard"),
+            Err(ArgError::InvalidValue(..))
+        ));
+        assert!(matches!(
+            parse("--width 3 --height 3 --mines 9"),
+            Err(ArgError::InvalidBoard(MineError::TooManyMines { .. }))
+        ));
+        assert!(matches!(
+            parse("--width 0"),
+            Err(ArgError::InvalidBoard(MineError::EmptyBoard))
+        ));
+    }
 }
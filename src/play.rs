@@ -0,0 +1,703 @@
+use crate::{
+    compute_cell_adjacency, opened_numbers, CascadeRule, Cell, CellState, CellType, Flag,
+    GameState, GameStatus, Grid, MineError, MisflagFeedback, Point, PracticeMode, Rules,
+    WinCondition, WinFlags,
+};
+use std::cmp::min;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+// Degenerate boards follow the same rule as any other: the game is won by
+// opening its last safe cell, or enough of them under a win threshold below
+// 1.0. A board with no mines is won by the first
+// click, and a board that is all mines, like a 1x1 with its one mine, has
+// nothing to open and can only be lost.
+fn is_game_won(state: &GameState) -> bool {
+    let flagged = match state.rules.win_condition {
+        WinCondition::OpenAllSafe => true,
+        // A mine opened in lives mode can't be flagged, so it counts as found
+        WinCondition::FlagAllMines => state.grid.iter().all(|cell| match cell.cell_type {
+            CellType::Mine => matches!(
+                cell.state,
+                CellState::Opened | CellState::Unopened(Flag::Sure)
+            ),
+            CellType::Empty { .. } => cell.state != CellState::Unopened(Flag::Sure),
+        }),
+    };
+    let has_safe = state.grid.points().any(|point| {
+        matches!(state.grid[point].cell_type, CellType::Empty { .. }) && !state.grid.is_wall(point)
+    });
+    flagged && has_safe && enough_safe_opened(state)
+}
+
+// The win threshold's share of the safe cells is opened, walls left out. At
+// 1.0 that is every one of them, counted exactly. At 0.0 the first opened
+// safe cell is enough.
+fn enough_safe_opened(state: &GameState) -> bool {
+    let grid = &state.grid;
+    let threshold = state.rules.effective_win_threshold();
+    if threshold >= 1.0 {
+        return all_safe_opened(grid);
+    }
+    let safe = grid
+        .points()
+        .filter(|&point| {
+            matches!(grid[point].cell_type, CellType::Empty { .. }) && !grid.is_wall(point)
+        })
+        .collect::<Vec<Point>>();
+    let opened = safe
+        .iter()
+        .filter(|&&point| grid[point].state == CellState::Opened)
+        .count();
+    opened > 0 && opened as f64 >= threshold * safe.len() as f64
+}
+
+fn all_safe_opened(grid: &Grid) -> bool {
+    grid.iter().all(|cell| match cell {
+        Cell {
+            cell_type: CellType::Empty { adjacent_mines: _ },
+            state: CellState::Opened,
+        } => true,
+        Cell {
+            cell_type: CellType::Empty { adjacent_mines: _ },
+            state: CellState::Unopened(_),
+        } => false,
+        Cell {
+            cell_type: CellType::Mine,
+            state: _,
+        } => true,
+    })
+}
+
+// Every mutator ends here. Once all safe cells are opened the game is won,
+// after the flags too under FlagAllMines, and under WinFlags::FlagMines the
+// mines left closed are flagged for show.
+pub fn check_and_finalize(state: GameState) -> GameState {
+    match state.status {
+        GameStatus::InProgress if is_game_won(&state) => {
+            let mut grid = state.grid;
+            if state.rules.flag_mines_on_win == WinFlags::FlagMines {
+                for point in grid.points().collect::<Vec<Point>>() {
+                    if let (CellType::Mine, CellState::Unopened(_)) =
+                        (grid[point].cell_type, grid[point].state)
+                    {
+                        grid[point].state = CellState::Unopened(Flag::Sure);
+                    }
+                }
+            }
+            GameState {
+                status: GameStatus::Won,
+                grid,
+                ..state
+            }
+        }
+        _ => state,
+    }
+}
+
+// A win with nothing off about it: every safe cell opened and no mine opened,
+// so not a win in lives mode after a hit, nor a state edited by hand
+pub fn is_clean_solve(state: &GameState) -> bool {
+    state.status == GameStatus::Won
+        && state.grid.iter().all(|cell| match cell.cell_type {
+            CellType::Empty { .. } => cell.state == CellState::Opened,
+            CellType::Mine => cell.state != CellState::Opened,
+        })
+}
+
+// Checks what every mutator keeps true, for tests and debug builds: numbers
+// match the mines, walls stay opened and safe, the status agrees with the
+// board, lives and open limit, the losing mine is an opened mine, and only
+// opened cells carry distinct ticks from the grid's count.
+pub fn debug_validate(state: &GameState) -> Result<(), MineError> {
+    let invalid = |reason: String| Err(MineError::InvalidState(reason));
+    let grid = &state.grid;
+
+    let mut ticks = HashSet::new();
+    for point in grid.points() {
+        let (x, y) = point;
+        let cell = grid[point];
+        let touching = compute_cell_adjacency(state, point);
+        match cell.cell_type {
+            CellType::Empty { adjacent_mines } if adjacent_mines != touching => {
+                return invalid(format!(
+                    "({}, {}) shows {} but touches {} mines",
+                    x, y, adjacent_mines, touching
+                ));
+            }
+            CellType::Mine if grid.is_wall(point) => {
+                return invalid(format!("({}, {}) is a wall with a mine", x, y));
+            }
+            _ if grid.is_wall(point) && cell.state != CellState::Opened => {
+                return invalid(format!("({}, {}) is a closed wall", x, y));
+            }
+            _ => (),
+        }
+        match (grid.open_order(point), cell.state) {
+            (Some(_), CellState::Unopened(_)) => {
+                return invalid(format!("({}, {}) has an open tick but is closed", x, y));
+            }
+            (Some(tick), _) if tick >= grid.ticks || !ticks.insert(tick) => {
+                return invalid(format!("({}, {}) has tick {} out of order", x, y, tick));
+            }
+            _ => (),
+        }
+    }
+
+    match state.status {
+        GameStatus::InProgress if state.lives == 0 => {
+            invalid("in progress without lives".to_string())
+        }
+        GameStatus::InProgress if state.opens_left() == Some(0) => {
+            invalid("in progress without opens left".to_string())
+        }
+        GameStatus::InProgress if is_game_won(state) => {
+            invalid("in progress but already won".to_string())
+        }
+        GameStatus::Won if !enough_safe_opened(state) => {
+            invalid("won with safe cells closed".to_string())
+        }
+        _ => match state.lost_at {
+            Some((x, y)) if state.status != GameStatus::Lost => {
+                invalid(format!("lost at ({}, {}) but not lost", x, y))
+            }
+            Some((x, y))
+                if grid[(x, y)]
+                    != (Cell {
+                        cell_type: CellType::Mine,
+                        state: CellState::Opened,
+                    }) =>
+            {
+                invalid(format!(
+                    "lost at ({}, {}), which is not an opened mine",
+                    x, y
+                ))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+pub fn open_cell(state: GameState, point: Point) -> GameState {
+    let counts = state.status == GameStatus::InProgress
+        && state.rules.open_flag_policy.opens(state.grid[point].state);
+    let state = reveal(state, point);
+    if counts {
+        spend_open(state)
+    } else {
+        state
+    }
+}
+
+// Counts an opening click and ends the game once no more are allowed, unless
+// the click just won it
+fn spend_open(state: GameState) -> GameState {
+    let state = GameState {
+        opens: state.opens + 1,
+        ..state
+    };
+    match (state.status, state.opens_left()) {
+        (GameStatus::InProgress, Some(0)) => GameState {
+            status: GameStatus::Lost,
+            ..state
+        },
+        _ => state,
+    }
+}
+
+// open_cell without counting the click
+fn reveal(state: GameState, point: Point) -> GameState {
+    match state.status {
+        GameStatus::InProgress => {
+            let mut grid = state.grid;
+            let cell = grid[point];
+            match cell.state {
+                _ if !state.rules.open_flag_policy.opens(cell.state) => GameState { grid, ..state },
+                _ => {
+                    if let (CellType::Mine, PracticeMode::On) =
+                        (cell.cell_type, state.rules.practice_mode)
+                    {
+                        // The flag can be the last one FlagAllMines waits for
+                        grid[point].state = CellState::Unopened(Flag::Sure);
+                        return check_and_finalize(GameState {
+                            mistakes: state.mistakes + 1,
+                            grid,
+                            ..state
+                        });
+                    }
+                    grid.open(point);
+                    grid.last_click = Some(point);
+                    match cell.cell_type {
+                        CellType::Mine => {
+                            // The hit mine stays opened so it can't be stepped on again
+                            let lives = state.lives.saturating_sub(1);
+                            GameState {
+                                status: if lives == 0 {
+                                    GameStatus::Lost
+                                } else {
+                                    GameStatus::InProgress
+                                },
+                                lives,
+                                lost_at: if lives == 0 { Some(point) } else { None },
+                                grid,
+                                ..state
+                            }
+                        }
+                        CellType::Empty { adjacent_mines } => {
+                            if adjacent_mines == 0 {
+                                cascade(&mut grid, point, state.rules);
+                            }
+                            check_and_finalize(GameState { grid, ..state })
+                        }
+                    }
+                }
+            }
+        }
+        _ => state,
+    }
+}
+
+// Open everything reachable from an opened zero, breadth first. The rules decide
+// which flagged cells are left alone and how far past the numbers it goes.
+fn cascade(grid: &mut Grid, point: Point, rules: Rules) {
+    let neighbors = Arc::clone(&grid.neighbors);
+    let mut pending = VecDeque::from([point]);
+    let mut border = Vec::new();
+    while let Some(zero) = pending.pop_front() {
+        for &adj in neighbors.of(zero) {
+            let cell = grid[adj];
+            if rules.cascade_flag_policy.opens(cell.state) {
+                grid.open(adj);
+                match cell.cell_type {
+                    CellType::Empty { adjacent_mines: 0 } => pending.push_back(adj),
+                    _ => border.push(adj),
+                }
+            }
+        }
+    }
+
+    if let CascadeRule::OpenOneMore = rules.cascade_rule {
+        for number in border {
+            for &adj in neighbors.of(number) {
+                let cell = grid[adj];
+                if matches!(cell.cell_type, CellType::Empty { .. })
+                    && rules.cascade_flag_policy.opens(cell.state)
+                {
+                    grid.open(adj);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OpenStep {
+    Clicked,
+    Cascade,
+    ExtraRing,
+}
+
+struct OpenIter<'a> {
+    state: &'a mut GameState,
+    clicked: Option<Point>,
+    // Opened but not yet yielded, in breadth first order
+    pending: VecDeque<(Point, OpenStep)>,
+    border: Vec<Point>,
+    counts: bool,
+}
+
+impl Iterator for OpenIter<'_> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if let Some(point) = self.clicked.take() {
+            let cell = self.state.grid[point];
+            match (self.state.status, cell.state) {
+                (GameStatus::InProgress, CellState::Unopened(_))
+                    if self.state.rules.open_flag_policy.opens(cell.state) =>
+                {
+                    if let (CellType::Mine, PracticeMode::On) =
+                        (cell.cell_type, self.state.rules.practice_mode)
+                    {
+                        self.state.grid[point].state = CellState::Unopened(Flag::Sure);
+                        self.state.mistakes += 1;
+                        let state = std::mem::replace(self.state, GameState::empty(0, 0));
+                        *self.state = spend_open(check_and_finalize(state));
+                        return None;
+                    }
+                    // The click is settled when the iterator runs out
+                    self.counts = true;
+                    self.state.grid.open(point);
+                    self.state.grid.last_click = Some(point);
+                    if let CellType::Mine = cell.cell_type {
+                        self.state.lives = self.state.lives.saturating_sub(1);
+                        if self.state.lives == 0 {
+                            self.state.status = GameStatus::Lost;
+                            self.state.lost_at = Some(point);
+                        }
+                        return Some(point);
+                    }
+                    self.pending.push_back((point, OpenStep::Clicked));
+                }
+                _ => return None,
+            }
+        }
+
+        if self.pending.is_empty() {
+            let neighbors = Arc::clone(&self.state.grid.neighbors);
+            let policy = self.state.rules.cascade_flag_policy;
+            for number in std::mem::take(&mut self.border) {
+                for &adj in neighbors.of(number) {
+                    let cell = self.state.grid[adj];
+                    if matches!(cell.cell_type, CellType::Empty { .. }) && policy.opens(cell.state)
+                    {
+                        self.state.grid.open(adj);
+                        self.pending.push_back((adj, OpenStep::ExtraRing));
+                    }
+                }
+            }
+        }
+
+        let Some((point, step)) = self.pending.pop_front() else {
+            let state = check_and_finalize(std::mem::replace(self.state, GameState::empty(0, 0)));
+            *self.state = if std::mem::take(&mut self.counts) {
+                spend_open(state)
+            } else {
+                state
+            };
+            return None;
+        };
+
+        match (self.state.grid[point].cell_type, step) {
+            (_, OpenStep::ExtraRing) => (),
+            (CellType::Empty { adjacent_mines: 0 }, _) => {
+                let neighbors = Arc::clone(&self.state.grid.neighbors);
+                let policy = self.state.rules.cascade_flag_policy;
+                for &adj in neighbors.of(point) {
+                    if policy.opens(self.state.grid[adj].state) {
+                        self.state.grid.open(adj);
+                        self.pending.push_back((adj, OpenStep::Cascade));
+                    }
+                }
+            }
+            (_, OpenStep::Cascade) => {
+                if let CascadeRule::OpenOneMore = self.state.rules.cascade_rule {
+                    self.border.push(point);
+                }
+            }
+            _ => (),
+        }
+        Some(point)
+    }
+}
+
+// Same as open_cell, but the cascade advances one yielded cell at a time so a
+// UI can animate it. The status is settled once the iterator runs out.
+pub fn open_iter(state: &mut GameState, point: Point) -> impl Iterator<Item = Point> + '_ {
+    OpenIter {
+        state,
+        clicked: Some(point),
+        pending: VecDeque::new(),
+        border: Vec::new(),
+        counts: false,
+    }
+}
+
+pub fn change_flag(state: GameState, point: Point, flag: Flag) -> GameState {
+    match state.status {
+        GameStatus::InProgress => {
+            let mut grid = state.grid;
+            let cell = grid[point];
+            match cell.state {
+                CellState::Opened => GameState { grid, ..state },
+                _ => {
+                    grid[point] = Cell {
+                        state: CellState::Unopened(flag),
+                        ..cell
+                    };
+                    let misflagged = flag == Flag::Sure
+                        && matches!(cell.cell_type, CellType::Empty { .. })
+                        && state.rules.feedback_on_misflag == MisflagFeedback::On;
+                    let nudge = if misflagged {
+                        grid.adjacent(point)
+                            .iter()
+                            .filter_map(|&adj| match grid[adj] {
+                                Cell {
+                                    cell_type: CellType::Empty { adjacent_mines },
+                                    state: CellState::Unopened(Flag::Unflagged),
+                                } => Some((adj, adjacent_mines)),
+                                _ => None,
+                            })
+                            .min_by_key(|&(_, adjacent_mines)| adjacent_mines)
+                    } else {
+                        None
+                    };
+                    let state = check_and_finalize(GameState { grid, ..state });
+                    // The engine opens the nudge, so it isn't one of the
+                    // player's opens under Rules.max_opens
+                    match nudge {
+                        Some((adj, _)) => reveal(state, adj),
+                        None => state,
+                    }
+                }
+            }
+        }
+        _ => state,
+    }
+}
+
+// The status change_flag would leave, without touching the state. Only a
+// flag win, the last mine flagged under FlagAllMines, or the cell a misflag
+// nudge opens can change it. Peeks at the layout like would_lose, since a
+// flag that wins has to be on a mine.
+#[cfg(feature = "assist")]
+pub fn status_if_flagged(state: &GameState, point: Point, flag: Flag) -> GameStatus {
+    change_flag(state.clone(), point, flag).status
+}
+
+// Mines still waiting for a Sure flag, the flags left to place under
+// FlagAllMines if every one of them goes on a mine. Unlike remaining_mines
+// a flag on a safe cell doesn't count down, so this peeks at the layout
+// like would_lose. A mine opened in lives mode counts as found.
+#[cfg(feature = "assist")]
+pub fn flags_needed(state: &GameState) -> usize {
+    state
+        .grid
+        .iter()
+        .filter(|cell| cell.cell_type == CellType::Mine)
+        .filter(|cell| {
+            matches!(
+                cell.state,
+                CellState::Unopened(Flag::Unflagged | Flag::Unsure)
+            )
+        })
+        .count()
+}
+
+pub fn flag_all_unopened(state: GameState, flag: Flag) -> GameState {
+    match state.status {
+        GameStatus::InProgress => {
+            let mut grid = state.grid;
+            for point in grid.points().collect::<Vec<Point>>() {
+                if let CellState::Unopened(Flag::Unflagged) = grid[point].state {
+                    grid[point].state = CellState::Unopened(flag);
+                }
+            }
+            check_and_finalize(GameState { grid, ..state })
+        }
+        _ => state,
+    }
+}
+
+// The neighbors a chord on this point would open. Only Sure flags count toward
+// satisfying the number, and Unsure cells are opened along with unflagged ones
+// since the player hasn't committed to them.
+pub fn highlight_safe(state: &GameState, point: Point) -> Vec<Point> {
+    match state.grid[point] {
+        Cell {
+            cell_type: CellType::Empty { adjacent_mines },
+            state: CellState::Opened,
+        } => {
+            // Only a satisfied number can be chorded
+            if state.neighbor_summary(point).sure.len() != usize::from(adjacent_mines) {
+                return Vec::new();
+            }
+
+            state
+                .grid
+                .adjacent(point)
+                .iter()
+                .copied()
+                .filter(|&adj_point| {
+                    matches!(
+                        state.grid[adj_point].state,
+                        CellState::Unopened(Flag::Unflagged | Flag::Unsure)
+                    )
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Opened numbers with exactly as many Sure flags around them as they show, the
+// ones highlight_safe agrees to chord, in row order. A UI can gray them out
+// as done. A wrong flag can make a number look satisfied, so a chord on it
+// isn't always safe.
+pub fn satisfied_numbers(state: &GameState) -> Vec<Point> {
+    opened_numbers(state)
+        .into_iter()
+        .filter(|&(point, number)| state.neighbor_summary(point).sure.len() == usize::from(number))
+        .map(|(point, _)| point)
+        .collect()
+}
+
+// How many safe cells chording every satisfied number would open, chording
+// again as long as the new openings satisfy more numbers. Played out on a
+// copy without the open limit, and a chain a wrong flag leads onto a mine
+// stops there.
+pub fn forced_open_count(state: &GameState) -> usize {
+    let closed_safe = |state: &GameState| {
+        state
+            .grid
+            .iter()
+            .filter(|cell| {
+                matches!(cell.cell_type, CellType::Empty { .. }) && cell.state != CellState::Opened
+            })
+            .count()
+    };
+    let mut played = state.clone();
+    played.rules.max_opens = None;
+    let played = open_flag_implied_safe(played);
+    closed_safe(state) - closed_safe(&played)
+}
+
+// The "trust my flags" assist: takes every Sure flag as a mine and chords
+// every number they satisfy, again and again until none is left. Each chord
+// is a click like any other. A wrong flag makes a number look satisfied, and
+// chording it can open a mine and lose the game.
+pub fn open_flag_implied_safe(state: GameState) -> GameState {
+    let mut state = state;
+    loop {
+        let chordable = satisfied_numbers(&state)
+            .into_iter()
+            .filter(|&point| !highlight_safe(&state, point).is_empty())
+            .collect::<Vec<Point>>();
+        if chordable.is_empty() || state.status != GameStatus::InProgress {
+            return state;
+        }
+        state = chordable.into_iter().fold(state, chord);
+    }
+}
+
+// Open the neighbors of a number satisfied by Sure flags, see highlight_safe
+// The whole chord is one opening click
+pub fn chord(state: GameState, point: Point) -> GameState {
+    let cells = highlight_safe(&state, point);
+    if state.status != GameStatus::InProgress || cells.is_empty() {
+        return state;
+    }
+    spend_open(cells.into_iter().fold(state, reveal))
+}
+
+// What a left click does in most UIs: opens a closed cell and chords an
+// opened one. Chording an unsatisfied number, or an opened mine or zero,
+// leaves the state as it is, as chord does.
+pub fn smart_click(state: GameState, point: Point) -> GameState {
+    match state.grid[point].state {
+        CellState::Opened => chord(state, point),
+        CellState::Unopened(_) => open_cell(state, point),
+    }
+}
+
+// Shows the whole solution. The game counts as lost since it wasn't earned.
+pub fn surrender(state: GameState) -> GameState {
+    let mut grid = state.grid;
+    for point in grid.points().collect::<Vec<Point>>() {
+        match grid[point].cell_type {
+            CellType::Empty { .. } => grid.open(point),
+            CellType::Mine => {
+                if let CellState::Unopened(_) = grid[point].state {
+                    grid[point].state = CellState::Unopened(Flag::Sure);
+                }
+            }
+        }
+    }
+
+    GameState {
+        status: GameStatus::Lost,
+        grid,
+        ..state
+    }
+}
+
+// Two players' progress on the same board as one state, for co-op. Cells
+// opened by either are opened, in a's order and then b's. Flags on cells
+// still closed are combined taking Sure over Unsure over none. The game is
+// lost if either player lost it, with the fewer lives left of the two, and
+// a's rules apply.
+pub fn merge_states(a: &GameState, b: &GameState) -> Result<GameState, MineError> {
+    if a.grid.width() != b.grid.width()
+        || a.grid.height() != b.grid.height()
+        || a.mine_positions() != b.mine_positions()
+    {
+        return Err(MineError::LayoutMismatch);
+    }
+
+    let mut grid = a.grid.clone();
+    // Cells b opened without a tick, as on a board built by hand or read
+    // back from a render, come after the ticked ones in row order
+    let mut opened_by_b = b
+        .grid
+        .points()
+        .filter(|&point| b.grid[point].state == CellState::Opened)
+        .map(|point| {
+            (
+                b.grid.open_order(point).is_none(),
+                b.grid.open_order(point),
+                point,
+            )
+        })
+        .collect::<Vec<(bool, Option<u32>, Point)>>();
+    opened_by_b.sort_unstable();
+    for (_, _, point) in opened_by_b {
+        grid.open(point);
+    }
+    let rank = |flag: Flag| match flag {
+        Flag::Unflagged => 0,
+        Flag::Unsure => 1,
+        Flag::Sure => 2,
+    };
+    for point in b.grid.points() {
+        if let (CellState::Unopened(ours), CellState::Unopened(theirs)) =
+            (grid[point].state, b.grid[point].state)
+        {
+            if rank(theirs) > rank(ours) {
+                grid[point].state = CellState::Unopened(theirs);
+            }
+        }
+    }
+
+    let lost = a.status == GameStatus::Lost || b.status == GameStatus::Lost;
+    Ok(check_and_finalize(GameState {
+        status: if lost {
+            GameStatus::Lost
+        } else {
+            GameStatus::InProgress
+        },
+        grid,
+        lives: min(a.lives, b.lives),
+        mistakes: a.mistakes.max(b.mistakes),
+        opens: a.opens.max(b.opens),
+        lost_at: a.lost_at.or(b.lost_at),
+        rules: a.rules,
+    }))
+}
+
+// Peeks at the hidden layout to warn before a risky click
+#[cfg(feature = "assist")]
+pub fn would_lose(state: &GameState, point: Point) -> bool {
+    let cell = state.grid[point];
+    cell.cell_type == CellType::Mine
+        && state.rules.open_flag_policy.opens(cell.state)
+        && matches!(state.status, GameStatus::InProgress)
+}
+
+// The closed safe cells whose opening, cascade included, would win the game
+// right away, in row order. Peeks at the layout like would_lose, and is
+// usually empty until the last few cells.
+#[cfg(feature = "assist")]
+pub fn winning_moves(state: &GameState) -> Vec<Point> {
+    if state.status != GameStatus::InProgress {
+        return Vec::new();
+    }
+    state
+        .grid
+        .points()
+        .filter(|&point| {
+            let cell = state.grid[point];
+            cell.cell_type != CellType::Mine && state.rules.open_flag_policy.opens(cell.state)
+        })
+        .filter(|&point| open_cell(state.clone(), point).status == GameStatus::Won)
+        .collect()
+}
@@ -0,0 +1,7 @@
+// The types and functions most games need, for a single glob import:
+//
+//   use rusty_mines::prelude::*;
+pub use crate::{
+    change_flag, chord, initial_state, initial_state_seeded, open_cell, Cell, CellState, CellType,
+    Difficulty, Flag, Game, GameState, GameStatus, MineError, Point,
+};
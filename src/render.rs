@@ -0,0 +1,278 @@
+use crate::{Cell, CellState, CellType, CellView, Flag, GameState, Point};
+use std::fmt;
+
+fn number_char(adjacent_mines: u8) -> char {
+    match adjacent_mines {
+        0 => ' ',
+        n => char::from(b'0' + n),
+    }
+}
+
+// Glyphs for render_styled. The default draws the same characters as render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderStyle {
+    pub closed: String,
+    pub unsure: String,
+    pub flag: String,
+    pub mine: String,
+    pub empty: String,
+    pub wall: String,
+    // An opened cell whose number is hidden by fog
+    pub fogged: String,
+    // Glyphs for 1 to 8
+    pub numbers: [String; 8],
+    pub origin: Origin,
+}
+
+// The screen corner the point (0, 0) is drawn in. Only presentation changes,
+// points are the same whichever origin is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Origin {
+    #[default]
+    TopLeft,
+    // Math style, y counting up from the bottom of the screen
+    BottomLeft,
+}
+
+impl Origin {
+    fn y_of(&self, row: usize, height: usize) -> usize {
+        match self {
+            Origin::TopLeft => row,
+            Origin::BottomLeft => height - 1 - row,
+        }
+    }
+}
+
+impl Default for RenderStyle {
+    fn default() -> RenderStyle {
+        RenderStyle {
+            closed: ".".to_string(),
+            unsure: "?".to_string(),
+            flag: "F".to_string(),
+            mine: "*".to_string(),
+            empty: " ".to_string(),
+            wall: WALL_CHAR.to_string(),
+            fogged: "-".to_string(),
+            numbers: std::array::from_fn(|index| (index + 1).to_string()),
+            origin: Origin::TopLeft,
+        }
+    }
+}
+
+impl RenderStyle {
+    fn glyph(&self, view: CellView) -> &str {
+        match view {
+            CellView::Closed(Flag::Unflagged) => &self.closed,
+            CellView::Closed(Flag::Unsure) => &self.unsure,
+            CellView::Closed(Flag::Sure) => &self.flag,
+            CellView::Mine | CellView::ExplodedMine | CellView::RevealedMine => &self.mine,
+            CellView::Number(0) => &self.empty,
+            CellView::Number(n) => &self.numbers[n as usize - 1],
+            CellView::Wall => &self.wall,
+            CellView::Opened => &self.fogged,
+        }
+    }
+
+    fn glyphs(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.closed,
+            &self.unsure,
+            &self.flag,
+            &self.mine,
+            &self.empty,
+            &self.wall,
+            &self.fogged,
+        ]
+        .into_iter()
+        .chain(self.numbers.iter())
+        .map(|glyph| glyph.as_str())
+    }
+}
+
+// Terminal columns taken by the text: two for East Asian wide and fullwidth
+// characters and emoji, none for combining marks, joiners and variation
+// selectors, one for everything else. A small stand-in for the unicode-width
+// tables, good enough for the glyphs a board is drawn with.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x0000..=0x001F
+            | 0x007F..=0x009F
+            | 0x0300..=0x036F
+            | 0x200B..=0x200F
+            | 0x20D0..=0x20FF
+            | 0xFE00..=0xFE0F
+            | 0x1F3FB..=0x1F3FF
+            | 0xE0100..=0xE01EF => 0,
+            0x1100..=0x115F
+            | 0x231A..=0x231B
+            | 0x23E9..=0x23EC
+            | 0x23F0
+            | 0x23F3
+            | 0x25FD..=0x25FE
+            | 0x2614..=0x2615
+            | 0x2648..=0x2653
+            | 0x267F
+            | 0x2693
+            | 0x26A1
+            | 0x26AA..=0x26AB
+            | 0x26BD..=0x26BE
+            | 0x26C4..=0x26C5
+            | 0x26CE
+            | 0x26D4
+            | 0x26EA
+            | 0x26F2..=0x26F3
+            | 0x26F5
+            | 0x26FA
+            | 0x26FD
+            | 0x2705
+            | 0x270A..=0x270B
+            | 0x2728
+            | 0x274C
+            | 0x274E
+            | 0x2753..=0x2755
+            | 0x2757
+            | 0x2795..=0x2797
+            | 0x27B0
+            | 0x27BF
+            | 0x2B1B..=0x2B1C
+            | 0x2B50
+            | 0x2B55
+            | 0x2E80..=0x303E
+            | 0x3041..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F004
+            | 0x1F0CF
+            | 0x1F18E
+            | 0x1F191..=0x1F19A
+            | 0x1F200..=0x1F2FF
+            | 0x1F300..=0x1F64F
+            | 0x1F680..=0x1F6FF
+            | 0x1F7E0..=0x1F7EB
+            | 0x1F900..=0x1F9FF
+            | 0x1FA70..=0x1FAFF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+// Walls look the same in every drawing of the board and in saves
+pub(crate) const WALL_CHAR: char = '#';
+
+fn view_char(cell: Cell) -> char {
+    match cell.state {
+        CellState::Unopened(Flag::Unflagged) => '.',
+        CellState::Unopened(Flag::Unsure) => '?',
+        CellState::Unopened(Flag::Sure) => 'F',
+        CellState::Opened => solution_char(cell),
+    }
+}
+
+fn solution_char(cell: Cell) -> char {
+    match cell.cell_type {
+        CellType::Empty { adjacent_mines } => number_char(adjacent_mines),
+        CellType::Mine => '*',
+    }
+}
+
+impl GameState {
+    // The player view, one line per row. Clears buf first so a game loop can
+    // reuse one buffer every frame.
+    pub fn render_into(&self, buf: &mut String) {
+        self.render_into_with_origin(buf, Origin::default());
+    }
+
+    pub fn render(&self) -> String {
+        self.render_with_origin(Origin::default())
+    }
+
+    // Same as render_into with the rows ordered for the origin
+    pub fn render_into_with_origin(&self, buf: &mut String, origin: Origin) {
+        buf.clear();
+        buf.reserve((self.grid.width() + 1) * self.grid.height());
+        for row in 0..self.grid.height() {
+            let y = origin.y_of(row, self.grid.height());
+            buf.extend((0..self.grid.width()).map(|x| self.view_char_at((x, y))));
+            buf.push('\n');
+        }
+    }
+
+    pub fn render_with_origin(&self, origin: Origin) -> String {
+        let mut out = String::new();
+        self.render_into_with_origin(&mut out, origin);
+        out
+    }
+
+    // The player view drawn with the style's glyphs. Every cell is padded to
+    // the display width of the widest glyph, so columns stay aligned when
+    // some glyphs take two terminal columns.
+    pub fn render_styled(&self, style: &RenderStyle) -> String {
+        let width = style.glyphs().map(display_width).max().unwrap_or(0);
+        let mut out = String::new();
+        for row in 0..self.grid.height() {
+            let y = style.origin.y_of(row, self.grid.height());
+            for x in 0..self.grid.width() {
+                let glyph = style.glyph(self.view_of((x, y)));
+                out.push_str(glyph);
+                out.extend(std::iter::repeat_n(' ', width - display_width(glyph)));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // The point drawn at a screen row and column, rows counting down from the
+    // top of the screen, if it is on the board
+    pub fn point_at_rc(&self, row: usize, col: usize, origin: Origin) -> Option<Point> {
+        match row < self.grid.height() && col < self.grid.width() {
+            true => Some((col, origin.y_of(row, self.grid.height()))),
+            false => None,
+        }
+    }
+
+    pub fn view_at_rc(&self, row: usize, col: usize, origin: Origin) -> Option<CellView> {
+        self.point_at_rc(row, col, origin)
+            .map(|point| self.view_of(point))
+    }
+
+    // Player view with the hidden solution below it, for debugging
+    pub fn pretty(&self) -> String {
+        let mut out = String::from("view:\n");
+        for y in 0..self.grid.height() {
+            out.push_str("  ");
+            out.extend((0..self.grid.width()).map(|x| self.view_char_at((x, y))));
+            out.push('\n');
+        }
+        out.push_str("solution:\n");
+        for y in 0..self.grid.height() {
+            out.push_str("  ");
+            out.extend(
+                (0..self.grid.width()).map(|x| match self.grid.is_wall((x, y)) {
+                    true => WALL_CHAR,
+                    false => solution_char(self.grid[(x, y)]),
+                }),
+            );
+            out.push('\n');
+        }
+        out
+    }
+
+    fn view_char_at(&self, point: Point) -> char {
+        match self.grid.is_wall(point) {
+            true => WALL_CHAR,
+            false => view_char(self.grid[point]),
+        }
+    }
+}
+
+// The player view as render draws it, Debug being left to the derive
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
@@ -0,0 +1,615 @@
+use crate::{
+    change_flag, chord, initial_state, initial_state_seeded, load_game, open_cell, save_game,
+    validate_params, CellState, Flag, Game, GameState, GameStatus, MineError, Point,
+};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Open(Point),
+    Flag(Point, Flag),
+    Chord(Point),
+}
+
+// Why replaying a move log failed, with the index of the offending move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    InvalidBoard(MineError),
+    OutOfBounds { index: usize, point: Point },
+    AlreadyOpened { index: usize, point: Point },
+    NotChordable { index: usize, point: Point },
+    GameOver { index: usize },
+    PastEnd { index: usize, moves: usize },
+    InvalidData { offset: usize, reason: String },
+    InvalidLog { line: usize, reason: String },
+    Io(io::ErrorKind),
+    // A value export_replay can't write as a u32, named with the value
+    TooLarge(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::InvalidBoard(err) => write!(f, "invalid board: {}", err),
+            ReplayError::OutOfBounds { index, point } => {
+                write!(f, "move {} at {:?} is outside the board", index, point)
+            }
+            ReplayError::AlreadyOpened { index, point } => {
+                write!(f, "move {} at {:?} targets an opened cell", index, point)
+            }
+            ReplayError::NotChordable { index, point } => {
+                write!(f, "move {} chords {:?} which is not opened", index, point)
+            }
+            ReplayError::GameOver { index } => {
+                write!(f, "move {} comes after the game ended", index)
+            }
+            ReplayError::PastEnd { index, moves } => {
+                write!(f, "move {} is past the end of a {} move log", index, moves)
+            }
+            ReplayError::InvalidData { offset, reason } => {
+                write!(f, "invalid replay data at byte {}: {}", offset, reason)
+            }
+            ReplayError::InvalidLog { line, reason } => {
+                write!(f, "move log line {}: {}", line, reason)
+            }
+            ReplayError::Io(kind) => write!(f, "reading the move log failed: {}", kind),
+            ReplayError::TooLarge(value) => {
+                write!(f, "{} does not fit in a replay file", value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+pub fn apply_move(state: GameState, action: Move) -> GameState {
+    match action {
+        Move::Open(point) => open_cell(state, point),
+        Move::Flag(point, flag) => change_flag(state, point, flag),
+        Move::Chord(point) => chord(state, point),
+    }
+}
+
+pub fn replay(
+    seed: u64,
+    width: usize,
+    height: usize,
+    mines: usize,
+    moves: &[Move],
+) -> Result<GameState, ReplayError> {
+    validate_params(width, height, mines).map_err(ReplayError::InvalidBoard)?;
+    let state = initial_state_seeded(width, height, mines, seed);
+    replay_moves(state, moves, 0)
+}
+
+// Re-derives a claimed win from the seed and moves instead of trusting a
+// submitted state. A move log that fails to replay is not a win. The board
+// is rebuilt from the seed alone on the default rules, so a game with a safe
+// first click, lives or other rules can't be checked here.
+pub fn verify_win(seed: u64, width: usize, height: usize, mines: usize, moves: &[Move]) -> bool {
+    matches!(
+        replay(seed, width, height, mines, moves),
+        Ok(GameState {
+            status: GameStatus::Won,
+            ..
+        })
+    )
+}
+
+// Applies a move log to a board, rejecting moves a real game could not have
+// made. Moves are numbered from first_index in errors.
+fn replay_moves(
+    state: GameState,
+    moves: &[Move],
+    first_index: usize,
+) -> Result<GameState, ReplayError> {
+    moves
+        .iter()
+        .enumerate()
+        .map(|(index, action)| (first_index + index, action))
+        .try_fold(state, |state, (index, &action)| {
+            check_move(&state, index, action)?;
+            Ok(apply_move(state, action))
+        })
+}
+
+// Whether a real game could make the move on the state
+fn check_move(state: &GameState, index: usize, action: Move) -> Result<(), ReplayError> {
+    let (Move::Open(point) | Move::Flag(point, _) | Move::Chord(point)) = action;
+    if !state.grid.in_bounds(point) {
+        return Err(ReplayError::OutOfBounds { index, point });
+    }
+    if !matches!(state.status, GameStatus::InProgress) {
+        return Err(ReplayError::GameOver { index });
+    }
+    match (action, state.grid[point].state) {
+        (Move::Open(_) | Move::Flag(..), CellState::Opened) => {
+            Err(ReplayError::AlreadyOpened { index, point })
+        }
+        (Move::Chord(_), CellState::Unopened(_)) => Err(ReplayError::NotChordable { index, point }),
+        _ => Ok(()),
+    }
+}
+
+// Moves between the cached states of a Replay
+const SNAPSHOT_INTERVAL: usize = 32;
+
+// A recorded game that can be scrubbed to any move. The whole log is checked
+// up front, and a state is kept every SNAPSHOT_INTERVAL moves so a jump only
+// replays the moves since the last one.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    moves: Vec<Move>,
+    snapshots: Vec<GameState>,
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        width: usize,
+        height: usize,
+        mines: usize,
+        moves: Vec<Move>,
+    ) -> Result<Replay, ReplayError> {
+        validate_params(width, height, mines).map_err(ReplayError::InvalidBoard)?;
+        let mut snapshots = vec![initial_state_seeded(width, height, mines, seed)];
+        for (chunk_index, chunk) in moves.chunks(SNAPSHOT_INTERVAL).enumerate() {
+            let last = snapshots[chunk_index].clone();
+            let state = replay_moves(last, chunk, chunk_index * SNAPSHOT_INTERVAL)?;
+            if chunk.len() == SNAPSHOT_INTERVAL {
+                snapshots.push(state);
+            }
+        }
+
+        Ok(Replay {
+            seed,
+            width,
+            height,
+            mines,
+            moves,
+            snapshots,
+        })
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    // The board after the first index moves, 0 being the fresh board
+    pub fn state_at(&self, index: usize) -> Result<GameState, ReplayError> {
+        if index > self.moves.len() {
+            return Err(ReplayError::PastEnd {
+                index,
+                moves: self.moves.len(),
+            });
+        }
+        let start = index / SNAPSHOT_INTERVAL * SNAPSHOT_INTERVAL;
+        let snapshot = self.snapshots[start / SNAPSHOT_INTERVAL].clone();
+        replay_moves(snapshot, &self.moves[start..index], start)
+    }
+
+    // For a lost game, the board just before the losing move and the point
+    // that move clicked: the mine itself, or the number when a chord lost.
+    // Moves past the end of a game are rejected, so the losing move is the
+    // last one.
+    pub fn losing_context(&self) -> Option<(GameState, Point)> {
+        let (&last, earlier) = self.moves.split_last()?;
+        let before = self.state_at(earlier.len()).ok()?;
+        match apply_move(before.clone(), last).status {
+            GameStatus::Lost => {
+                let (Move::Open(point) | Move::Flag(point, _) | Move::Chord(point)) = last;
+                Some((before, point))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Snapshots follow from the rest, so they are left out
+impl PartialEq for Replay {
+    fn eq(&self, other: &Replay) -> bool {
+        (self.seed, self.width, self.height, self.mines, &self.moves)
+            == (
+                other.seed,
+                other.width,
+                other.height,
+                other.mines,
+                &other.moves,
+            )
+    }
+}
+
+impl Eq for Replay {}
+
+const REPLAY_MAGIC: &[u8; 4] = b"RMRP";
+
+const REPLAY_FORMAT_VERSION: u8 = 1;
+
+// Binary replay file for outside tools. Every integer is little endian:
+//
+//   4 bytes  magic "RMRP"
+//   u8       format version, 1
+//   u64      seed
+//   u32 x3   width, height, mine count
+//   u32 x2   x and y of every mine, in row order
+//   u32      move count
+//   10 bytes per move: u8 kind (0 open, 1 flag, 2 chord), u32 x, u32 y and
+//            u8 flag (0 unflagged, 1 unsure, 2 sure, always 0 unless a flag)
+//
+// The mines follow from the seed but are written out so a reader doesn't
+// need this crate's generator. Moves carry no times since replays don't
+// record any. A replay too big for the u32 fields is refused rather than
+// written truncated.
+pub fn export_replay(replay: &Replay) -> Result<Vec<u8>, ReplayError> {
+    let field = |name: &str, value: usize| {
+        u32::try_from(value)
+            .map(u32::to_le_bytes)
+            .map_err(|_| ReplayError::TooLarge(format!("{} {}", name, value)))
+    };
+    // Checked before the mines are generated from them
+    let width = field("width", replay.width)?;
+    let height = field("height", replay.height)?;
+    let mine_count = field("mine count", replay.mines)?;
+    let mines = initial_state_seeded(replay.width, replay.height, replay.mines, replay.seed)
+        .mine_positions();
+    let mut bytes = Vec::with_capacity(29 + mines.len() * 8 + replay.moves.len() * 10);
+    bytes.extend_from_slice(REPLAY_MAGIC);
+    bytes.push(REPLAY_FORMAT_VERSION);
+    bytes.extend_from_slice(&replay.seed.to_le_bytes());
+    bytes.extend_from_slice(&width);
+    bytes.extend_from_slice(&height);
+    bytes.extend_from_slice(&mine_count);
+    for (x, y) in mines {
+        bytes.extend_from_slice(&field("x", x)?);
+        bytes.extend_from_slice(&field("y", y)?);
+    }
+    bytes.extend_from_slice(&field("move count", replay.moves.len())?);
+    for &action in replay.moves.iter() {
+        let (kind, (x, y), flag) = match action {
+            Move::Open(point) => (0, point, Flag::Unflagged),
+            Move::Flag(point, flag) => (1, point, flag),
+            Move::Chord(point) => (2, point, Flag::Unflagged),
+        };
+        bytes.push(kind);
+        bytes.extend_from_slice(&field("x", x)?);
+        bytes.extend_from_slice(&field("y", y)?);
+        bytes.push(match flag {
+            Flag::Unflagged => 0,
+            Flag::Unsure => 1,
+            Flag::Sure => 2,
+        });
+    }
+    Ok(bytes)
+}
+
+// Most cells an imported replay may have. The header is untrusted, and a
+// board is generated from it before any move is read.
+const MAX_REPLAY_CELLS: usize = 1 << 20;
+
+// Reads export_replay's format, checking the mines against the seed and the
+// moves the way Replay::new does
+pub fn import_replay(bytes: &[u8]) -> Result<Replay, ReplayError> {
+    let mut reader = ReplayReader { bytes, offset: 0 };
+    if reader.take(4)? != REPLAY_MAGIC {
+        return Err(reader.invalid(0, "not a rusty-mines replay"));
+    }
+    let version = reader.u8()?;
+    if version != REPLAY_FORMAT_VERSION {
+        return Err(reader.invalid(4, &format!("unknown format version {}", version)));
+    }
+    let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+    let size_offset = reader.offset;
+    let width = reader.u32()? as usize;
+    let height = reader.u32()? as usize;
+    let mines = reader.u32()? as usize;
+    if width
+        .checked_mul(height)
+        .is_none_or(|cells| cells > MAX_REPLAY_CELLS)
+    {
+        return Err(reader.invalid(
+            size_offset,
+            &format!("{}x{} board is larger than a replay allows", width, height),
+        ));
+    }
+    validate_params(width, height, mines).map_err(ReplayError::InvalidBoard)?;
+    // The mines and the move count must all be there before anything is
+    // allocated or generated for them
+    if bytes.len() - reader.offset < mines * 8 + 4 {
+        return Err(reader.invalid(bytes.len(), "replay ends early"));
+    }
+
+    let mines_offset = reader.offset;
+    let mut positions = Vec::with_capacity(mines);
+    for _ in 0..mines {
+        positions.push((reader.u32()? as usize, reader.u32()? as usize));
+    }
+    if positions != initial_state_seeded(width, height, mines, seed).mine_positions() {
+        return Err(reader.invalid(mines_offset, "mines don't match the seed"));
+    }
+
+    let count = reader.u32()? as usize;
+    let mut moves = Vec::with_capacity(count.min(bytes.len() / 10));
+    for _ in 0..count {
+        let offset = reader.offset;
+        let kind = reader.u8()?;
+        let point = (reader.u32()? as usize, reader.u32()? as usize);
+        let flag = match reader.u8()? {
+            0 => Flag::Unflagged,
+            1 => Flag::Unsure,
+            2 => Flag::Sure,
+            flag => return Err(reader.invalid(offset + 9, &format!("unknown flag {}", flag))),
+        };
+        moves.push(match kind {
+            0 => Move::Open(point),
+            1 => Move::Flag(point, flag),
+            2 => Move::Chord(point),
+            kind => return Err(reader.invalid(offset, &format!("unknown move kind {}", kind))),
+        });
+    }
+    if reader.offset != bytes.len() {
+        return Err(reader.invalid(reader.offset, "trailing bytes after the moves"));
+    }
+
+    Replay::new(seed, width, height, mines, moves)
+}
+
+struct ReplayReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl ReplayReader<'_> {
+    fn invalid(&self, offset: usize, reason: &str) -> ReplayError {
+        ReplayError::InvalidData {
+            offset,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&[u8], ReplayError> {
+        let Some(taken) = self.bytes.get(self.offset..self.offset + count) else {
+            return Err(self.invalid(self.bytes.len(), "replay ends early"));
+        };
+        self.offset += count;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+// Plays a game from line commands, for test automation:
+//
+//   seed 42        seed for the next new board
+//   new 9 9 10     start a board of width, height and mines
+//   open 0 0       also flag, unsure, unflag and chord
+//
+// Blank lines and lines starting with # are skipped.
+pub fn run_script(script: &str) -> Result<GameState, MineError> {
+    let mut seed = None;
+    let mut state: Option<GameState> = None;
+
+    for (index, line) in script.lines().enumerate() {
+        let invalid = |reason: String| MineError::InvalidScript {
+            line: index + 1,
+            reason,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args = words
+            .map(|word| {
+                word.parse::<u64>()
+                    .map_err(|_| invalid(format!("{} is not a number", word)))
+            })
+            .collect::<Result<Vec<u64>, MineError>>()?;
+        let expect_args = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(invalid(format!("{} takes {} arguments", command, count)))
+            }
+        };
+
+        match command {
+            "seed" => {
+                expect_args(1)?;
+                seed = Some(args[0]);
+            }
+            "new" => {
+                expect_args(3)?;
+                let (width, height, mines) = (args[0] as usize, args[1] as usize, args[2] as usize);
+                validate_params(width, height, mines).map_err(|err| invalid(err.to_string()))?;
+                state = Some(match seed {
+                    Some(seed) => initial_state_seeded(width, height, mines, seed),
+                    None => initial_state(width, height, mines),
+                });
+            }
+            "open" | "flag" | "unsure" | "unflag" | "chord" => {
+                expect_args(2)?;
+                let point = (args[0] as usize, args[1] as usize);
+                let current = state
+                    .take()
+                    .ok_or_else(|| invalid(format!("{} before new", command)))?;
+                if !current.grid.in_bounds(point) {
+                    return Err(invalid(MineError::OutOfBounds(point).to_string()));
+                }
+                let action = script_move(command, point).expect("move command");
+                state = Some(apply_move(current, action));
+            }
+            _ => return Err(invalid(format!("unknown command {}", command))),
+        }
+    }
+
+    state.ok_or(MineError::InvalidScript {
+        line: script.lines().count(),
+        reason: "no board was created".to_string(),
+    })
+}
+
+// Script commands for moves, the inverse of script_line
+fn script_move(command: &str, point: Point) -> Option<Move> {
+    match command {
+        "open" => Some(Move::Open(point)),
+        "flag" => Some(Move::Flag(point, Flag::Sure)),
+        "unsure" => Some(Move::Flag(point, Flag::Unsure)),
+        "unflag" => Some(Move::Flag(point, Flag::Unflagged)),
+        "chord" => Some(Move::Chord(point)),
+        _ => None,
+    }
+}
+
+fn script_line(action: Move) -> String {
+    let (command, (x, y)) = match action {
+        Move::Open(point) => ("open", point),
+        Move::Flag(point, Flag::Sure) => ("flag", point),
+        Move::Flag(point, Flag::Unsure) => ("unsure", point),
+        Move::Flag(point, Flag::Unflagged) => ("unflag", point),
+        Move::Chord(point) => ("chord", point),
+    };
+    format!("{} {} {}\n", command, x, y)
+}
+
+// Appends a game to a log as it is played, so a crash loses at most the move
+// being written. The log starts with the game as save_game writes it, so its
+// rules, lives, walls and relocation RNG come back with it, and whether it
+// plays forced moves, then has the moves after a moves line:
+//
+//   # rusty-mines move log
+//   assist off
+//   rusty-mines 12
+//   ...
+//   safe-first-click on
+//   board
+//   .........
+//   moves
+//   open 4 4
+//   flag 0 1
+//
+// Every line is flushed as soon as it is written.
+pub struct MoveLogWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> MoveLogWriter<W> {
+    pub fn new(writer: W, game: &Game) -> io::Result<MoveLogWriter<W>> {
+        let mut log = MoveLogWriter { writer };
+        write!(
+            log.writer,
+            "# rusty-mines move log\nassist {}\n{}moves\n",
+            if game.assist { "on" } else { "off" },
+            save_game(game)
+        )?;
+        log.writer.flush()?;
+        Ok(log)
+    }
+
+    pub fn append(&mut self, action: Move) -> io::Result<()> {
+        self.writer.write_all(script_line(action).as_bytes())?;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// Reads back a MoveLogWriter log, playing its moves through the game it
+// starts with. A last line cut off without its newline is a move that was
+// being written during a crash and is dropped.
+pub fn replay_from_log<R: BufRead>(reader: R) -> Result<Game, ReplayError> {
+    let mut assist = None;
+    let mut save = String::new();
+    let mut save_start = 0;
+    let mut game: Option<Game> = None;
+    let mut index = 0;
+    let mut reader = reader;
+    let mut line = String::new();
+    for number in 1.. {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| ReplayError::Io(err.kind()))?;
+        if read == 0 || !line.ends_with('\n') {
+            break;
+        }
+        let invalid = |reason: String| ReplayError::InvalidLog {
+            line: number,
+            reason,
+        };
+        let text = line.trim();
+        if assist.is_none() && (text.is_empty() || text.starts_with('#')) {
+            continue;
+        }
+
+        let Some(game) = &mut game else {
+            match (assist, text) {
+                (None, "assist on") => assist = Some(true),
+                (None, "assist off") => assist = Some(false),
+                (None, _) => return Err(invalid(format!("unexpected line {}", text))),
+                (Some(assist), "moves") => {
+                    let mut loaded = load_game(&save).map_err(|err| match err {
+                        MineError::InvalidSave { line, reason } => ReplayError::InvalidLog {
+                            line: save_start + line - 1,
+                            reason,
+                        },
+                        err => invalid(err.to_string()),
+                    })?;
+                    loaded.assist = assist;
+                    game = Some(loaded);
+                }
+                (Some(_), _) => {
+                    if save.is_empty() {
+                        save_start = number;
+                    }
+                    save.push_str(&line);
+                }
+            }
+            continue;
+        };
+
+        let mut words = text.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args = words
+            .map(|word| {
+                word.parse::<u64>()
+                    .map_err(|_| invalid(format!("{} is not a number", word)))
+            })
+            .collect::<Result<Vec<u64>, ReplayError>>()?;
+        let action = match args.as_slice() {
+            &[x, y] => script_move(command, (x as usize, y as usize))
+                .ok_or_else(|| invalid(format!("unknown move {}", command)))?,
+            _ => return Err(invalid(format!("unexpected line {}", text))),
+        };
+        check_move(&game.state, index, action)?;
+        game.play(action);
+        index += 1;
+    }
+
+    game.ok_or_else(|| ReplayError::InvalidLog {
+        line: 1,
+        reason: "log has no game".to_string(),
+    })
+}
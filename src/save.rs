@@ -0,0 +1,710 @@
+use crate::{
+    check_and_finalize, deduce_all, validate_params, CascadeFlagPolicy, CascadeRule, Cell,
+    CellState, CellType, Flag, Game, GameRng, GameState, GameStatus, MineError, MisflagFeedback,
+    OpenFlagPolicy, Point, PracticeMode, WinCondition, WinFlags, WALL_CHAR,
+};
+use std::collections::HashMap;
+
+// Version written by save_state. Version 1 saves only had the status and the
+// board, lives and rules came in version 2, practice mode in version 3 and
+// the win condition in version 4, misflag feedback in version 5, the open
+// limit in version 6, the mine that lost the game in version 7, the open
+// flag policy in version 8, walls in version 9, the fog radius in version
+// 10, flagging mines on a win in version 11 and the win threshold in version
+// 12.
+pub const SAVE_VERSION: u32 = 12;
+
+// A save split into its header fields and board rows, before any defaults
+// are filled in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveValue {
+    pub version: u32,
+    pub fields: HashMap<String, String>,
+    pub rows: Vec<String>,
+}
+
+// Board characters, each encoding both the cell type and its state
+fn save_char(cell: Cell) -> char {
+    match (cell.cell_type, cell.state) {
+        (CellType::Empty { .. }, CellState::Opened) => 'o',
+        (CellType::Empty { .. }, CellState::Unopened(Flag::Unflagged)) => '.',
+        (CellType::Empty { .. }, CellState::Unopened(Flag::Unsure)) => '?',
+        (CellType::Empty { .. }, CellState::Unopened(Flag::Sure)) => 'f',
+        (CellType::Mine, CellState::Opened) => 'X',
+        (CellType::Mine, CellState::Unopened(Flag::Unflagged)) => '*',
+        (CellType::Mine, CellState::Unopened(Flag::Unsure)) => '!',
+        (CellType::Mine, CellState::Unopened(Flag::Sure)) => 'F',
+    }
+}
+
+fn parse_save_char(c: char) -> Option<(bool, CellState)> {
+    match c {
+        'o' => Some((false, CellState::Opened)),
+        '.' => Some((false, CellState::Unopened(Flag::Unflagged))),
+        '?' => Some((false, CellState::Unopened(Flag::Unsure))),
+        'f' => Some((false, CellState::Unopened(Flag::Sure))),
+        'X' => Some((true, CellState::Opened)),
+        '*' => Some((true, CellState::Unopened(Flag::Unflagged))),
+        '!' => Some((true, CellState::Unopened(Flag::Unsure))),
+        'F' => Some((true, CellState::Unopened(Flag::Sure))),
+        _ => None,
+    }
+}
+
+// Plain text save of the whole state:
+//
+//   rusty-mines 2
+//   status in-progress
+//   lives 1
+//   cascade stop-at-numbers
+//   flags respect-all
+//   practice off
+//   mistakes 0
+//   win open-all-safe
+//   feedback off
+//   max-opens none
+//   opens 0
+//   lost-at none
+//   open-flagged protect-sure
+//   fog none
+//   win-flags keep
+//   win-threshold 1
+//   board
+//   oo*.
+//   #o.#
+//
+// Numbers are not stored, they follow from the mines. Walls of a masked
+// board are stored as #.
+pub fn save_state(state: &GameState) -> String {
+    let status = match state.status {
+        GameStatus::InProgress => "in-progress",
+        GameStatus::Lost => "lost",
+        GameStatus::Won => "won",
+    };
+    let cascade = match state.rules.cascade_rule {
+        CascadeRule::StopAtNumbers => "stop-at-numbers",
+        CascadeRule::OpenOneMore => "open-one-more",
+    };
+    let flags = match state.rules.cascade_flag_policy {
+        CascadeFlagPolicy::RespectAllFlags => "respect-all",
+        CascadeFlagPolicy::RespectSureOnly => "respect-sure",
+    };
+    let practice = match state.rules.practice_mode {
+        PracticeMode::Off => "off",
+        PracticeMode::On => "on",
+    };
+    let win = match state.rules.win_condition {
+        WinCondition::OpenAllSafe => "open-all-safe",
+        WinCondition::FlagAllMines => "flag-all-mines",
+    };
+    let feedback = match state.rules.feedback_on_misflag {
+        MisflagFeedback::Off => "off",
+        MisflagFeedback::On => "on",
+    };
+    let max_opens = match state.rules.max_opens {
+        Some(max_opens) => max_opens.to_string(),
+        None => "none".to_string(),
+    };
+    let open_flagged = match state.rules.open_flag_policy {
+        OpenFlagPolicy::ProtectSure => "protect-sure",
+        OpenFlagPolicy::OpenAll => "open-all",
+    };
+    let win_flags = match state.rules.flag_mines_on_win {
+        WinFlags::Keep => "keep",
+        WinFlags::FlagMines => "flag-mines",
+    };
+    let fog = match state.rules.fog_radius {
+        Some(radius) => radius.to_string(),
+        None => "none".to_string(),
+    };
+    let lost_at = match state.lost_at {
+        Some((x, y)) => format!("{},{}", x, y),
+        None => "none".to_string(),
+    };
+
+    let mut save = format!(
+        "rusty-mines {}\nstatus {}\nlives {}\ncascade {}\nflags {}\npractice {}\nmistakes {}\nwin {}\nfeedback {}\nmax-opens {}\nopens {}\nlost-at {}\nopen-flagged {}\nfog {}\nwin-flags {}\nwin-threshold {}\nboard\n",
+        SAVE_VERSION,
+        status,
+        state.lives,
+        cascade,
+        flags,
+        practice,
+        state.mistakes,
+        win,
+        feedback,
+        max_opens,
+        state.opens,
+        lost_at,
+        open_flagged,
+        fog,
+        win_flags,
+        state.rules.win_threshold
+    );
+    for y in 0..state.grid.height() {
+        save.extend(
+            (0..state.grid.width()).map(|x| match state.grid.is_wall((x, y)) {
+                true => WALL_CHAR,
+                false => save_char(state.grid[(x, y)]),
+            }),
+        );
+        save.push('\n');
+    }
+    save
+}
+
+// Splits a save of any version into its parts without interpreting them
+pub fn parse_save(text: &str) -> Result<SaveValue, MineError> {
+    let invalid = |line: usize, reason: String| MineError::InvalidSave { line, reason };
+    let mut lines = text.lines().enumerate();
+
+    let version = match lines.next().map(|(_, line)| line.split_whitespace()) {
+        Some(mut words) => match (words.next(), words.next(), words.next()) {
+            (Some("rusty-mines"), Some(version), None) => version
+                .parse::<u32>()
+                .map_err(|_| invalid(1, format!("{} is not a version", version)))?,
+            _ => return Err(invalid(1, "missing rusty-mines header".to_string())),
+        },
+        None => return Err(invalid(1, "save is empty".to_string())),
+    };
+
+    let mut fields = HashMap::new();
+    let mut rows = Vec::new();
+    let mut in_board = false;
+    for (index, line) in lines {
+        let line = line.trim();
+        match (in_board, line.split_once(' ')) {
+            (true, _) => rows.push(line.to_string()),
+            (false, _) if line == "board" => in_board = true,
+            (false, Some((key, value))) => {
+                fields.insert(key.to_string(), value.trim().to_string());
+            }
+            (false, None) => return Err(invalid(index + 1, format!("{} has no value", line))),
+        }
+    }
+    if !in_board {
+        return Err(invalid(text.lines().count(), "missing board".to_string()));
+    }
+
+    Ok(SaveValue {
+        version,
+        fields,
+        rows,
+    })
+}
+
+// Builds the current state out of a save of any supported version, filling
+// in defaults for whatever older versions did not store
+pub fn migrate(value: SaveValue) -> Result<GameState, MineError> {
+    if value.version > SAVE_VERSION {
+        return Err(MineError::UnsupportedVersion {
+            version: value.version,
+            supported: SAVE_VERSION,
+        });
+    }
+    // Errors past the header can't point at a line once it is split, so they
+    // point at the header
+    let invalid = |reason: String| MineError::InvalidSave { line: 1, reason };
+    let field = |key: &str| value.fields.get(key).map(|value| value.as_str());
+
+    let height = value.rows.len();
+    let width = value.rows.first().map_or(0, |row| row.chars().count());
+    if value.rows.iter().any(|row| row.chars().count() != width) {
+        return Err(invalid("board rows differ in length".to_string()));
+    }
+    let cells = value
+        .rows
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|c| match c {
+                    // Walls come back opened by from_mines_masked below
+                    WALL_CHAR => Ok((false, CellState::Opened)),
+                    c => parse_save_char(c).ok_or_else(|| invalid(format!("unknown cell {}", c))),
+                })
+                .collect::<Result<Vec<(bool, CellState)>, MineError>>()
+        })
+        .collect::<Result<Vec<Vec<(bool, CellState)>>, MineError>>()?;
+    let mines = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| cells[y][x].0)
+        .collect::<Vec<Point>>();
+    validate_params(width, height, mines.len()).map_err(|err| invalid(err.to_string()))?;
+    let mask = value
+        .rows
+        .iter()
+        .any(|row| row.contains(WALL_CHAR))
+        .then(|| {
+            value
+                .rows
+                .iter()
+                .map(|row| row.chars().map(|c| c != WALL_CHAR).collect())
+                .collect()
+        });
+
+    let mut state = GameState::from_mines_masked(width, height, &mines, mask)
+        .map_err(|err| invalid(err.to_string()))?;
+    for point in state.grid.points().collect::<Vec<Point>>() {
+        let (x, y) = point;
+        match cells[y][x].1 {
+            CellState::Opened => state.grid.open(point),
+            flagged => state.grid[point].state = flagged,
+        }
+    }
+
+    state.status = match field("status") {
+        Some("in-progress") => GameStatus::InProgress,
+        Some("lost") => GameStatus::Lost,
+        Some("won") => GameStatus::Won,
+        Some(status) => return Err(invalid(format!("unknown status {}", status))),
+        None => return Err(invalid("missing status".to_string())),
+    };
+    // Version 1 had no lives or rules, which meant one life and the default
+    // rules
+    if value.version >= 2 {
+        state.lives = match field("lives").map(|lives| lives.parse::<u32>()) {
+            Some(Ok(lives)) => lives,
+            Some(Err(_)) => return Err(invalid("lives is not a number".to_string())),
+            None => return Err(invalid("missing lives".to_string())),
+        };
+        state.rules.cascade_rule = match field("cascade") {
+            Some("stop-at-numbers") => CascadeRule::StopAtNumbers,
+            Some("open-one-more") => CascadeRule::OpenOneMore,
+            Some(rule) => return Err(invalid(format!("unknown cascade rule {}", rule))),
+            None => return Err(invalid("missing cascade".to_string())),
+        };
+        state.rules.cascade_flag_policy = match field("flags") {
+            Some("respect-all") => CascadeFlagPolicy::RespectAllFlags,
+            Some("respect-sure") => CascadeFlagPolicy::RespectSureOnly,
+            Some(policy) => return Err(invalid(format!("unknown flag policy {}", policy))),
+            None => return Err(invalid("missing flags".to_string())),
+        };
+    }
+    // Practice mode came in version 3, before it nobody made mistakes
+    if value.version >= 3 {
+        state.rules.practice_mode = match field("practice") {
+            Some("off") => PracticeMode::Off,
+            Some("on") => PracticeMode::On,
+            Some(mode) => return Err(invalid(format!("unknown practice mode {}", mode))),
+            None => return Err(invalid("missing practice".to_string())),
+        };
+        state.mistakes = match field("mistakes").map(|mistakes| mistakes.parse::<u32>()) {
+            Some(Ok(mistakes)) => mistakes,
+            Some(Err(_)) => return Err(invalid("mistakes is not a number".to_string())),
+            None => return Err(invalid("missing mistakes".to_string())),
+        };
+    }
+    if value.version >= 4 {
+        state.rules.win_condition = match field("win") {
+            Some("open-all-safe") => WinCondition::OpenAllSafe,
+            Some("flag-all-mines") => WinCondition::FlagAllMines,
+            Some(condition) => return Err(invalid(format!("unknown win condition {}", condition))),
+            None => return Err(invalid("missing win".to_string())),
+        };
+    }
+    if value.version >= 5 {
+        state.rules.feedback_on_misflag = match field("feedback") {
+            Some("off") => MisflagFeedback::Off,
+            Some("on") => MisflagFeedback::On,
+            Some(feedback) => return Err(invalid(format!("unknown feedback {}", feedback))),
+            None => return Err(invalid("missing feedback".to_string())),
+        };
+    }
+    if value.version >= 6 {
+        state.rules.max_opens =
+            match field("max-opens").map(|max_opens| (max_opens, max_opens.parse::<u32>())) {
+                Some(("none", _)) => None,
+                Some((_, Ok(max_opens))) => Some(max_opens),
+                Some((_, Err(_))) => return Err(invalid("max-opens is not a number".to_string())),
+                None => return Err(invalid("missing max-opens".to_string())),
+            };
+        state.opens = match field("opens").map(|opens| opens.parse::<u32>()) {
+            Some(Ok(opens)) => opens,
+            Some(Err(_)) => return Err(invalid("opens is not a number".to_string())),
+            None => return Err(invalid("missing opens".to_string())),
+        };
+    }
+    if value.version >= 7 {
+        state.lost_at = match field("lost-at") {
+            Some("none") => None,
+            Some(point) => {
+                let parsed = point
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.parse::<usize>().ok()?, y.parse::<usize>().ok()?)));
+                match parsed {
+                    Some(point) if state.grid.in_bounds(point) => Some(point),
+                    _ => return Err(invalid(format!("{} is not a point on the board", point))),
+                }
+            }
+            None => return Err(invalid("missing lost-at".to_string())),
+        };
+    }
+    // Before version 8 Sure flags could be opened
+    state.rules.open_flag_policy = if value.version >= 8 {
+        match field("open-flagged") {
+            Some("protect-sure") => OpenFlagPolicy::ProtectSure,
+            Some("open-all") => OpenFlagPolicy::OpenAll,
+            Some(policy) => return Err(invalid(format!("unknown open flag policy {}", policy))),
+            None => return Err(invalid("missing open-flagged".to_string())),
+        }
+    } else {
+        OpenFlagPolicy::OpenAll
+    };
+    if value.version >= 10 {
+        state.rules.fog_radius = match field("fog").map(|fog| (fog, fog.parse::<usize>())) {
+            Some(("none", _)) => None,
+            Some((_, Ok(radius))) => Some(radius),
+            Some((_, Err(_))) => return Err(invalid("fog is not a number".to_string())),
+            None => return Err(invalid("missing fog".to_string())),
+        };
+    }
+    // Before version 11 a win always flagged the mines
+    state.rules.flag_mines_on_win = if value.version >= 11 {
+        match field("win-flags") {
+            Some("keep") => WinFlags::Keep,
+            Some("flag-mines") => WinFlags::FlagMines,
+            Some(flags) => return Err(invalid(format!("unknown win flags {}", flags))),
+            None => return Err(invalid("missing win-flags".to_string())),
+        }
+    } else {
+        WinFlags::FlagMines
+    };
+    // Thresholds out of range are saved as they were set and load as the
+    // threshold they play with
+    if value.version >= 12 {
+        state.rules.win_threshold = match field("win-threshold").map(str::parse::<f64>) {
+            Some(Ok(threshold)) => threshold,
+            Some(Err(_)) => return Err(invalid("win-threshold is not a number".to_string())),
+            None => return Err(invalid("missing win-threshold".to_string())),
+        };
+        state.rules.win_threshold = state.rules.effective_win_threshold();
+    }
+
+    Ok(state)
+}
+
+pub fn load_state(text: &str) -> Result<GameState, MineError> {
+    migrate(parse_save(text)?)
+}
+
+// A state save with the game's RNG and first click option added before the
+// board, so a resumed game relocates mines exactly as it would have without
+// the save:
+//
+//   rng 1234 2
+//   safe-first-click on
+//
+// Click counts, the first click, timing and the metadata are not kept and
+// start over.
+pub fn save_game(game: &Game) -> String {
+    let extra = format!(
+        "\nrng {} {}\nsafe-first-click {}\nboard\n",
+        game.rng.seed,
+        game.rng.words,
+        if game.safe_first_click { "on" } else { "off" }
+    );
+    save_state(&game.state).replacen("\nboard\n", &extra, 1)
+}
+
+pub fn load_game(text: &str) -> Result<Game, MineError> {
+    let value = parse_save(text)?;
+    let invalid = |reason: &str| MineError::InvalidSave {
+        line: 1,
+        reason: reason.to_string(),
+    };
+    let rng = match value
+        .fields
+        .get("rng")
+        .map(|rng| {
+            rng.split_whitespace()
+                .map(str::parse::<u64>)
+                .collect::<Vec<_>>()
+        })
+        .as_deref()
+    {
+        Some([Ok(seed), Ok(words)]) => GameRng::restore(*seed, *words),
+        Some(_) => return Err(invalid("rng is not a seed and a word count")),
+        None => return Err(invalid("missing rng")),
+    };
+    let safe_first_click = match value.fields.get("safe-first-click").map(String::as_str) {
+        Some("on") => true,
+        Some("off") => false,
+        Some(_) => return Err(invalid("unknown safe-first-click")),
+        None => return Err(invalid("missing safe-first-click")),
+    };
+
+    let mut game = Game::new(migrate(value)?);
+    game.rng = rng;
+    game.safe_first_click = safe_first_click;
+    Ok(game)
+}
+
+// URL safe, so codes can go in links as they are
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Unpadded base64 of the bytes
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |word, (index, &byte)| {
+            word | (byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..=chunk.len() {
+            out.push(BASE64[(word >> (18 - 6 * index) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .bytes()
+        .map(|c| {
+            BASE64
+                .iter()
+                .position(|&digit| digit == c)
+                .map(|d| d as u32)
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    let mut bytes = Vec::new();
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let word = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |word, (index, &digit)| word | digit << (18 - 6 * index));
+        bytes.extend((0..chunk.len() - 1).map(|index| (word >> (16 - 8 * index)) as u8));
+    }
+    Some(bytes)
+}
+
+// Just the layout of a board, much shorter than a save: the dimensions, then
+// the base64 of one bit per cell in row order, set for mines, the first cell
+// in the lowest bit of the first byte.
+//
+//   9x9.AKAIAEIAgAZBAAA
+//
+// Play state and walls are not kept.
+pub fn puzzle_code(state: &GameState) -> String {
+    let grid = &state.grid;
+    let mut bits = vec![0u8; (grid.width() * grid.height()).div_ceil(8)];
+    for (index, cell) in grid.iter().enumerate() {
+        if cell.cell_type == CellType::Mine {
+            bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+    format!(
+        "{}x{}.{}",
+        grid.width(),
+        grid.height(),
+        base64_encode(&bits)
+    )
+}
+
+// A fresh board, in progress with the default rules, from a puzzle code
+pub fn from_puzzle_code(code: &str) -> Result<GameState, MineError> {
+    let invalid = |reason: &str| MineError::InvalidPuzzleCode(reason.to_string());
+    let (size, layout) = code
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| invalid("missing the . after the dimensions"))?;
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(width, height)| {
+            Some((width.parse::<usize>().ok()?, height.parse::<usize>().ok()?))
+        })
+        .ok_or_else(|| invalid("dimensions are not WxH"))?;
+    let cells = width
+        .checked_mul(height)
+        .ok_or_else(|| invalid("board is too large"))?;
+    let bits = base64_decode(layout).ok_or_else(|| invalid("layout is not base64"))?;
+    if bits.len() != cells.div_ceil(8) {
+        return Err(invalid("layout does not match the dimensions"));
+    }
+    if (cells..bits.len() * 8).any(|index| bits[index / 8] & 1 << (index % 8) != 0) {
+        return Err(invalid("layout has mines past the last cell"));
+    }
+
+    let mines = (0..cells)
+        .filter(|&index| bits[index / 8] & 1 << (index % 8) != 0)
+        .map(|index| (index % width, index / width))
+        .collect::<Vec<Point>>();
+    validate_params(width, height, mines.len())?;
+    Ok(GameState::from_mines(width, height, &mines))
+}
+
+// A hash of the layout that is the same for every rotation and mirror image
+// of it, so a puzzle collection can spot a board it already has turned
+// around. Square boards are taken in all 8 orientations, others only in the
+// 4 that keep their width and height. The hash is 64 bit FNV-1a over the
+// dimensions and one byte per cell in row order, 1 for a mine and 2 for a
+// wall, and the smallest over the orientations is returned. Play state is
+// left out.
+pub fn canonical_fingerprint(state: &GameState) -> u64 {
+    let grid = &state.grid;
+    let (width, height) = (grid.width(), grid.height());
+    // Where each orientation moves (x, y). The ones swapping the axes only
+    // fit a square board. A board without cells has nothing to move.
+    let (w, h) = (width.saturating_sub(1), height.saturating_sub(1));
+    let mut orientations: Vec<Box<dyn Fn(Point) -> Point>> = vec![
+        Box::new(|(x, y)| (x, y)),
+        Box::new(move |(x, y)| (w - x, h - y)),
+        Box::new(move |(x, y)| (w - x, y)),
+        Box::new(move |(x, y)| (x, h - y)),
+    ];
+    if width == height {
+        orientations.push(Box::new(|(x, y)| (y, x)));
+        orientations.push(Box::new(move |(x, y)| (h - y, w - x)));
+        orientations.push(Box::new(move |(x, y)| (h - y, x)));
+        orientations.push(Box::new(move |(x, y)| (y, w - x)));
+    }
+
+    orientations
+        .iter()
+        .map(|orient| {
+            let mut cells = vec![0u8; width * height];
+            for point in grid.points() {
+                let (x, y) = orient(point);
+                cells[y * width + x] = match grid[point].cell_type {
+                    _ if grid.is_wall(point) => 2,
+                    CellType::Mine => 1,
+                    CellType::Empty { .. } => 0,
+                };
+            }
+            (width as u64)
+                .to_le_bytes()
+                .into_iter()
+                .chain((height as u64).to_le_bytes())
+                .chain(cells)
+                .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                    (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+                })
+        })
+        .min()
+        .expect("at least the board as it is")
+}
+
+// Reads back the player view drawn by render: digits and spaces are opened
+// numbers, '.' closed cells, 'F' and '?' flags, '*' an opened mine and '#' a
+// wall. Rows shorter than the widest are padded with spaces, as editors
+// drop trailing ones. What is behind the closed cells doesn't show, so the
+// mines are deduced from the numbers, and a closed cell next to a number
+// they don't settle is an error. Closed cells away from every number could
+// hold anything and come back as safe. The default rules apply, so an
+// opened mine lost the game.
+pub fn from_render(text: &str) -> Result<GameState, MineError> {
+    let invalid = |line: usize, reason: String| MineError::InvalidRender { line, reason };
+    let rows = text
+        .lines()
+        .map(|row| row.chars().collect::<Vec<char>>())
+        .collect::<Vec<Vec<char>>>();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let height = rows.len();
+    validate_params(width, height, 0)?;
+    let glyph = |(x, y): Point| rows[y].get(x).copied().unwrap_or(' ');
+
+    let mut opened_mines = Vec::new();
+    let mut mask = vec![vec![true; width]; height];
+    for (y, row) in mask.iter_mut().enumerate() {
+        for (x, playable) in row.iter_mut().enumerate() {
+            match glyph((x, y)) {
+                '*' => opened_mines.push((x, y)),
+                WALL_CHAR => *playable = false,
+                ' ' | '1'..='8' | '.' | 'F' | '?' => (),
+                other => return Err(invalid(y + 1, format!("unknown cell {:?}", other))),
+            }
+        }
+    }
+    let mask = mask
+        .iter()
+        .flatten()
+        .any(|&playable| !playable)
+        .then_some(mask);
+
+    // The numbers as drawn over closed cells of unknown content, for the
+    // solver to work out the mines from
+    let drawn = |mines: &[Point]| -> Result<GameState, MineError> {
+        let mut state = GameState::from_mines_masked(width, height, mines, mask.clone())?;
+        for point in state.grid.points().collect::<Vec<Point>>() {
+            let shown = match glyph(point) {
+                ' ' => Some(0),
+                digit @ '1'..='8' => Some(digit as u8 - b'0'),
+                _ => None,
+            };
+            match glyph(point) {
+                '*' => state.grid.open(point),
+                '.' => state.grid[point].state = CellState::Unopened(Flag::Unflagged),
+                '?' => state.grid[point].state = CellState::Unopened(Flag::Unsure),
+                'F' => state.grid[point].state = CellState::Unopened(Flag::Sure),
+                _ => (),
+            }
+            if let Some(adjacent_mines) = shown {
+                state.grid[point].cell_type = CellType::Empty { adjacent_mines };
+                state.grid.open(point);
+            }
+        }
+        Ok(state)
+    };
+    let guessed = drawn(&opened_mines)?;
+    let (safe, deduced) = deduce_all(&guessed);
+    for point in guessed.grid.points() {
+        let settled = deduced.contains(&point) || safe.contains(&point);
+        let numbered = guessed.grid.adjacent(point).iter().any(|&adj| {
+            guessed.grid[adj].state == CellState::Opened
+                && matches!(
+                    guessed.grid[adj].cell_type,
+                    CellType::Empty {
+                        adjacent_mines: 1..
+                    }
+                )
+        });
+        if matches!(guessed.grid[point].state, CellState::Unopened(_)) && numbered && !settled {
+            return Err(invalid(
+                point.1 + 1,
+                format!(
+                    "the numbers don't tell whether ({}, {}) is a mine",
+                    point.0, point.1
+                ),
+            ));
+        }
+    }
+
+    let mut mines = opened_mines.clone();
+    mines.extend(deduced);
+    mines.sort_unstable_by_key(|&(x, y)| (y, x));
+    validate_params(width, height, mines.len())?;
+    let mut state = GameState::from_mines_masked(width, height, &mines, mask.clone())?;
+    for point in guessed.grid.points() {
+        let cell = guessed.grid[point];
+        // The solver trusts the numbers, so one that doesn't fit shows here
+        if let (CellState::Opened, CellType::Empty { adjacent_mines }) =
+            (cell.state, cell.cell_type)
+        {
+            if state.grid[point].cell_type != cell.cell_type {
+                return Err(invalid(
+                    point.1 + 1,
+                    format!(
+                        "({}, {}) shows {} but the mines around it don't add up",
+                        point.0, point.1, adjacent_mines
+                    ),
+                ));
+            }
+        }
+        match cell.state {
+            CellState::Opened if !state.grid.is_wall(point) => state.grid.open(point),
+            _ => state.grid[point].state = cell.state,
+        }
+    }
+
+    match opened_mines.first() {
+        Some(&point) => Ok(GameState {
+            status: GameStatus::Lost,
+            lives: 0,
+            lost_at: Some(point),
+            ..state
+        }),
+        None => Ok(check_and_finalize(state)),
+    }
+}
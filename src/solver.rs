@@ -0,0 +1,1585 @@
+use crate::{
+    change_flag, open_cell, open_iter, Cell, CellState, CellType, Flag, GameState, GameStatus,
+    OpenFlagPolicy, Point,
+};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cmp::{min, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub fn adjacency_histogram(state: &GameState) -> [u32; 9] {
+    let mut histogram = [0; 9];
+    for point in state
+        .grid
+        .points()
+        .filter(|&point| !state.grid.is_wall(point))
+    {
+        if let CellType::Empty { adjacent_mines } = state.grid[point].cell_type {
+            histogram[usize::from(adjacent_mines)] += 1;
+        }
+    }
+    histogram
+}
+
+// Every opened number with closed neighbors, as those neighbors and how many
+// mines are left among them once mines opened in lives mode are counted
+fn constraints(state: &GameState) -> Vec<(Vec<Point>, usize)> {
+    numbered_constraints(state)
+        .into_iter()
+        .map(|(_, closed, mines)| (closed, mines))
+        .collect()
+}
+
+// Same as constraints, along with the numbered cell each one comes from
+fn numbered_constraints(state: &GameState) -> Vec<(Point, Vec<Point>, usize)> {
+    state
+        .grid
+        .points()
+        .filter_map(|point| {
+            number_constraint(state, point).map(|(closed, mines)| (point, closed, mines))
+        })
+        .collect()
+}
+
+// The closed neighbors of an opened number and how many mines they hold
+// besides the opened ones, if it has any closed neighbors
+fn number_constraint(state: &GameState, point: Point) -> Option<(Vec<Point>, usize)> {
+    let grid = &state.grid;
+    match grid[point] {
+        Cell {
+            cell_type: CellType::Empty { adjacent_mines },
+            state: CellState::Opened,
+        } => {
+            let mut closed = Vec::new();
+            let mut known_mines = 0;
+            for &adj in grid.adjacent(point) {
+                match grid[adj] {
+                    Cell {
+                        state: CellState::Unopened(_),
+                        ..
+                    } => closed.push(adj),
+                    Cell {
+                        cell_type: CellType::Mine,
+                        ..
+                    } => known_mines += 1,
+                    _ => (),
+                }
+            }
+            if closed.is_empty() {
+                None
+            } else {
+                Some((
+                    closed,
+                    usize::from(adjacent_mines).saturating_sub(known_mines),
+                ))
+            }
+        }
+        _ => None,
+    }
+}
+
+// Opened numbers that can't be right from what is visible around them: more
+// mines than the neighbors that are closed or opened mines, or fewer than the
+// mines already opened next to them. Only a corrupted state has any.
+pub fn find_conflicts(state: &GameState) -> Vec<Point> {
+    let grid = &state.grid;
+    opened_numbers(state)
+        .into_iter()
+        .filter(|&(point, number)| {
+            let adj = grid.adjacent(point);
+            let could_be_mines = adj
+                .iter()
+                .filter(|&&adj| {
+                    matches!(grid[adj].state, CellState::Unopened(_))
+                        || grid[adj].cell_type == CellType::Mine
+                })
+                .count();
+            let opened_mines = adj
+                .iter()
+                .filter(|&&adj| {
+                    grid[adj]
+                        == Cell {
+                            cell_type: CellType::Mine,
+                            state: CellState::Opened,
+                        }
+                })
+                .count();
+            usize::from(number) > could_be_mines || usize::from(number) < opened_mines
+        })
+        .map(|(point, _)| point)
+        .collect()
+}
+
+// The numbers the solver works from and the closed cells they touch. Flags
+// are ignored like everywhere in the solver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintGraph {
+    // Opened numbers with closed neighbors, with the mines left around each
+    pub numbers: Vec<(Point, usize)>,
+    // Closed cells next to at least one of the numbers, in row order
+    pub cells: Vec<Point>,
+    // A number and one of its closed neighbors
+    pub edges: Vec<(Point, Point)>,
+}
+
+impl ConstraintGraph {
+    // Graphviz source, numbers as boxes labeled with the mines left around
+    // them and closed cells as circles
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph constraints {\n");
+        for &((x, y), mines) in &self.numbers {
+            dot.push_str(&format!(
+                "  \"n{}_{}\" [shape=box, label=\"{}\"];\n",
+                x, y, mines
+            ));
+        }
+        for &(x, y) in &self.cells {
+            dot.push_str(&format!(
+                "  \"c{}_{}\" [shape=circle, label=\"{},{}\"];\n",
+                x, y, x, y
+            ));
+        }
+        for &((nx, ny), (cx, cy)) in &self.edges {
+            dot.push_str(&format!("  \"n{}_{}\" -- \"c{}_{}\";\n", nx, ny, cx, cy));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+pub fn constraint_graph(state: &GameState) -> ConstraintGraph {
+    let constraints = numbered_constraints(state);
+    let touched = constraints
+        .iter()
+        .flat_map(|(_, closed, _)| closed.iter().copied())
+        .collect::<HashSet<Point>>();
+    ConstraintGraph {
+        numbers: constraints
+            .iter()
+            .map(|&(point, _, mines)| (point, mines))
+            .collect(),
+        cells: state
+            .grid
+            .points()
+            .filter(|point| touched.contains(point))
+            .collect(),
+        edges: constraints
+            .iter()
+            .flat_map(|(number, closed, _)| closed.iter().map(|&cell| (*number, cell)))
+            .collect(),
+    }
+}
+
+// Every opened number in row order. Opened zeros are left out, they tell
+// nothing about closed cells.
+pub fn opened_numbers(state: &GameState) -> Vec<(Point, u8)> {
+    state
+        .grid
+        .points()
+        .filter_map(|point| match state.grid[point] {
+            Cell {
+                cell_type: CellType::Empty { adjacent_mines },
+                state: CellState::Opened,
+            } if adjacent_mines > 0 => Some((point, adjacent_mines)),
+            _ => None,
+        })
+        .collect()
+}
+
+// How pressed each opened number with closed neighbors is, as the player
+// sees it: the mines it still needs over the closed cells it could put them
+// in. Sure flags and opened mines count as its mines, 1.0 means every closed
+// neighbor must be a mine and 0.0 that they are all safe. Over-flagged
+// numbers report 0.0.
+pub fn number_tension(state: &GameState) -> HashMap<Point, f64> {
+    opened_numbers(state)
+        .into_iter()
+        .filter_map(|(point, number)| {
+            let summary = state.neighbor_summary(point);
+            let unknowns = summary.unopened.len() + summary.unsure.len();
+            if unknowns == 0 {
+                return None;
+            }
+            let opened_mines = summary
+                .opened
+                .iter()
+                .filter(|&&adj| state.grid[adj].cell_type == CellType::Mine)
+                .count();
+            let needed = usize::from(number).saturating_sub(summary.sure.len() + opened_mines);
+            Some((point, (needed as f64 / unknowns as f64).min(1.0)))
+        })
+        .collect()
+}
+
+// One step of the solver's reasoning: a cell proven safe or a mine by an
+// opened number, possibly together with a second number whose closed
+// neighbors all touch the first one too
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deduction {
+    pub point: Point,
+    pub mine: bool,
+    pub because: Point,
+    pub shows: u8,
+    pub using: Option<(Point, u8)>,
+}
+
+impl fmt::Display for Deduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (x, y) = self.point;
+        let (bx, by) = self.because;
+        let kind = if self.mine { "a mine" } else { "safe" };
+        write!(
+            f,
+            "({}, {}) is {} because ({}, {}) shows {}",
+            x, y, kind, bx, by, self.shows
+        )?;
+        match (self.using, self.mine) {
+            (None, true) => write!(f, " and every unknown neighbor left must be a mine"),
+            (None, false) => write!(f, " and all its mines are accounted for"),
+            (Some(((ux, uy), shows)), true) => write!(
+                f,
+                " and the neighbors it shares with ({}, {}), which shows {}, can't hold them all",
+                ux, uy, shows
+            ),
+            (Some(((ux, uy), shows)), false) => write!(
+                f,
+                " and the neighbors it shares with ({}, {}), which shows {}, already hold them all",
+                ux, uy, shows
+            ),
+        }
+    }
+}
+
+// Safe cells and mines that follow from the opened numbers alone. Flags are
+// player input and are not trusted.
+pub fn deduce_all(state: &GameState) -> (HashSet<Point>, HashSet<Point>) {
+    let (safe, mines, _) = deduce_all_explained(state);
+    (safe, mines)
+}
+
+// Deduced cells the player still has to act on: safe cells left closed and
+// mines without a Sure flag. Once none are left the next move is a guess.
+pub fn remaining_logical_moves(state: &GameState) -> usize {
+    let (safe, mines) = deduce_all(state);
+    let pending_safe = safe
+        .iter()
+        .filter(|&&point| state.grid[point].state != CellState::Opened)
+        .count();
+    let pending_mines = mines
+        .iter()
+        .filter(|&&point| state.grid[point].state != CellState::Unopened(Flag::Sure))
+        .count();
+    pending_safe + pending_mines
+}
+
+// Same as deduce_all, along with why each cell was deduced, in the order the
+// solver found them
+pub fn deduce_all_explained(state: &GameState) -> (HashSet<Point>, HashSet<Point>, Vec<Deduction>) {
+    let shows = |point: Point| match state.grid[point].cell_type {
+        CellType::Empty { adjacent_mines } => adjacent_mines,
+        CellType::Mine => 0,
+    };
+    let constraints = numbered_constraints(state)
+        .into_iter()
+        .map(|(number, cells, count)| (number, cells.into_iter().collect(), count))
+        .collect::<Vec<(Point, HashSet<Point>, usize)>>();
+
+    let mut safe = HashSet::new();
+    let mut mines = HashSet::new();
+    let mut deductions = Vec::new();
+    loop {
+        // Drop what is already known from every constraint
+        let reduced = constraints
+            .iter()
+            .map(|(number, cells, count)| {
+                let unknown = cells
+                    .iter()
+                    .filter(|point| !safe.contains(*point) && !mines.contains(*point))
+                    .copied()
+                    .collect::<HashSet<Point>>();
+                let known_mines = cells.iter().filter(|point| mines.contains(*point)).count();
+                (*number, unknown, count.saturating_sub(known_mines))
+            })
+            .filter(|(_, unknown, _)| !unknown.is_empty())
+            .collect::<Vec<(Point, HashSet<Point>, usize)>>();
+
+        let mut found = Vec::new();
+        for (number, unknown, count) in reduced.iter() {
+            if *count == 0 || *count == unknown.len() {
+                found.extend(unknown.iter().map(|&point| Deduction {
+                    point,
+                    mine: *count > 0,
+                    because: *number,
+                    shows: shows(*number),
+                    using: None,
+                }));
+            }
+        }
+
+        // When a constraint is contained in another, the leftover cells carry
+        // the difference of their counts
+        if found.is_empty() {
+            for (small_number, small, small_count) in reduced.iter() {
+                for (large_number, large, large_count) in reduced.iter() {
+                    if small.len() >= large.len() || !small.is_subset(large) {
+                        continue;
+                    }
+                    let rest = large.difference(small).copied().collect::<Vec<Point>>();
+                    let mine = match large_count.checked_sub(*small_count) {
+                        Some(0) => false,
+                        Some(count) if count == rest.len() => true,
+                        _ => continue,
+                    };
+                    found.extend(rest.into_iter().map(|point| Deduction {
+                        point,
+                        mine,
+                        because: *large_number,
+                        shows: shows(*large_number),
+                        using: Some((*small_number, shows(*small_number))),
+                    }));
+                }
+            }
+        }
+
+        if found.is_empty() {
+            return (safe, mines, deductions);
+        }
+        // Sets iterate in no particular order, so settle one before keeping
+        // the first reason found for each cell
+        found.sort_by_key(|deduction| (deduction.point.1, deduction.point.0));
+        for deduction in found {
+            let new = if deduction.mine {
+                mines.insert(deduction.point)
+            } else {
+                safe.insert(deduction.point)
+            };
+            if new {
+                deductions.push(deduction);
+            }
+        }
+    }
+}
+
+// What deduce_all finds, kept up to date move by move instead of worked out
+// again over the whole board. After a move only the numbers around the cells
+// it changed are read again, and the reasoning goes on from them and from
+// wherever new deductions lead. As deductions only ever add up, this ends
+// with the same cells deduce_all would give. Only for moves on one board: a
+// state from another board needs a new one.
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalSolver {
+    // Every opened number with closed neighbors, keyed by where it is
+    constraints: HashMap<Point, (HashSet<Point>, usize)>,
+    safe: HashSet<Point>,
+    mines: HashSet<Point>,
+}
+
+impl IncrementalSolver {
+    pub fn new(state: &GameState) -> IncrementalSolver {
+        let mut solver = IncrementalSolver::default();
+        solver.update(state, &state.grid.points().collect::<Vec<Point>>());
+        solver
+    }
+
+    // Catches up with state after a move that changed the given cells, see
+    // changed_cells
+    pub fn update(&mut self, state: &GameState, changed: &[Point]) {
+        let grid = &state.grid;
+        let mut dirty = Vec::new();
+        for &point in changed {
+            if grid[point].state == CellState::Opened {
+                self.safe.remove(&point);
+                self.mines.remove(&point);
+            }
+            for &number in grid.adjacent(point).iter().chain([&point]) {
+                match number_constraint(state, number) {
+                    Some((closed, mines)) => {
+                        self.constraints
+                            .insert(number, (closed.into_iter().collect(), mines));
+                        dirty.push(number);
+                    }
+                    None => {
+                        self.constraints.remove(&number);
+                    }
+                }
+            }
+        }
+        self.propagate(state, dirty);
+    }
+
+    // Closed cells proven safe, as in deduce_all
+    pub fn safe(&self) -> &HashSet<Point> {
+        &self.safe
+    }
+
+    // Closed cells proven to be mines, as in deduce_all
+    pub fn mines(&self) -> &HashSet<Point> {
+        &self.mines
+    }
+
+    // A number's cells not known yet and the mines left among them
+    fn reduced(&self, number: Point) -> Option<(HashSet<Point>, usize)> {
+        let (cells, mines) = self.constraints.get(&number)?;
+        let unknown = cells
+            .iter()
+            .filter(|point| !self.safe.contains(*point) && !self.mines.contains(*point))
+            .copied()
+            .collect::<HashSet<Point>>();
+        let known_mines = cells
+            .iter()
+            .filter(|point| self.mines.contains(*point))
+            .count();
+        match unknown.is_empty() {
+            true => None,
+            false => Some((unknown, mines.saturating_sub(known_mines))),
+        }
+    }
+
+    // The rules of deduce_all_explained, applied to the dirty numbers and
+    // to every number whose cells they settle, until nothing new turns up
+    fn propagate(&mut self, state: &GameState, mut dirty: Vec<Point>) {
+        let grid = &state.grid;
+        let mut queued = dirty.iter().copied().collect::<HashSet<Point>>();
+        while let Some(number) = dirty.pop() {
+            queued.remove(&number);
+            let Some((unknown, count)) = self.reduced(number) else {
+                continue;
+            };
+
+            let mut found = Vec::new();
+            if count == 0 || count == unknown.len() {
+                found.extend(unknown.iter().map(|&point| (point, count > 0)));
+            } else {
+                // Numbers sharing a cell with this one, compared both ways
+                let overlapping = unknown
+                    .iter()
+                    .flat_map(|&point| grid.adjacent(point).iter().copied())
+                    .filter(|&other| other != number && self.constraints.contains_key(&other))
+                    .collect::<HashSet<Point>>();
+                for other in overlapping {
+                    let Some((other_unknown, other_count)) = self.reduced(other) else {
+                        continue;
+                    };
+                    let pairs = [
+                        (&unknown, count, &other_unknown, other_count),
+                        (&other_unknown, other_count, &unknown, count),
+                    ];
+                    for (small, small_count, large, large_count) in pairs {
+                        if small.len() >= large.len() || !small.is_subset(large) {
+                            continue;
+                        }
+                        let rest = large.difference(small).copied().collect::<Vec<Point>>();
+                        let mine = match large_count.checked_sub(small_count) {
+                            Some(0) => false,
+                            Some(count) if count == rest.len() => true,
+                            _ => continue,
+                        };
+                        found.extend(rest.into_iter().map(|point| (point, mine)));
+                    }
+                }
+            }
+
+            for (point, mine) in found {
+                let new = match mine {
+                    true => self.mines.insert(point),
+                    false => self.safe.insert(point),
+                };
+                if !new {
+                    continue;
+                }
+                for &adj in grid.adjacent(point) {
+                    if self.constraints.contains_key(&adj) && queued.insert(adj) {
+                        dirty.push(adj);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Opens the first click and then every cell logic proves safe, until logic runs
+// out or the board is cleared
+fn solve_by_logic(state: &GameState, first_click: Point) -> GameState {
+    let mut state = open_cell(state.clone(), first_click);
+    while let GameStatus::InProgress = state.status {
+        let (safe, _) = deduce_all(&state);
+        let safe = openable(&state, safe);
+        if safe.is_empty() {
+            break;
+        }
+        for point in safe {
+            state = open_cell(state, point);
+        }
+    }
+    state
+}
+
+// Cells the solver proved safe that open_cell will open, leaving out the ones
+// a player's Sure flag protects
+fn openable(state: &GameState, safe: HashSet<Point>) -> Vec<Point> {
+    let policy = state.rules.open_flag_policy;
+    safe.into_iter()
+        .filter(|&point| policy.opens(state.grid[point].state))
+        .collect()
+}
+
+// Opens every provably safe cell and flags every provable mine, over and over
+// until only real decisions are left
+pub fn advance_forced(state: GameState) -> GameState {
+    let mut state = state;
+    while let GameStatus::InProgress = state.status {
+        let (safe, mines) = deduce_all(&state);
+        let safe = openable(&state, safe);
+        let unflagged_mines = mines
+            .into_iter()
+            .filter(|&point| state.grid[point].state != CellState::Unopened(Flag::Sure))
+            .collect::<Vec<Point>>();
+        if safe.is_empty() && unflagged_mines.is_empty() {
+            break;
+        }
+        for point in unflagged_mines {
+            state = change_flag(state, point, Flag::Sure);
+        }
+        for point in safe {
+            state = open_cell(state, point);
+        }
+    }
+    state
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagJustification {
+    // The numbers prove a mine under the flag
+    Confirmed,
+    // The numbers prove the flagged cell safe
+    Contradicted,
+    // Nothing proven either way yet
+    Speculative,
+}
+
+// Judges each Sure flag against what the opened numbers prove, without giving
+// away anything about cells the player hasn't flagged
+pub fn classify_flags(state: &GameState) -> HashMap<Point, FlagJustification> {
+    let (safe, mines) = deduce_all(state);
+    state
+        .grid
+        .points()
+        .filter(|&point| state.grid[point].state == CellState::Unopened(Flag::Sure))
+        .map(|point| {
+            let justification = if mines.contains(&point) {
+                FlagJustification::Confirmed
+            } else if safe.contains(&point) {
+                FlagJustification::Contradicted
+            } else {
+                FlagJustification::Speculative
+            };
+            (point, justification)
+        })
+        .collect()
+}
+
+pub fn is_solvable_without_guessing(state: &GameState, first_click: Point) -> bool {
+    matches!(solve_by_logic(state, first_click).status, GameStatus::Won)
+}
+
+// First clicks from which the whole board can be cleared by logic alone
+pub fn guess_free_openings(state: &GameState) -> Vec<Point> {
+    state
+        .grid
+        .points()
+        .filter(|&point| {
+            matches!(
+                state.grid[point],
+                Cell {
+                    cell_type: CellType::Empty { .. },
+                    state: CellState::Unopened(_),
+                }
+            )
+        })
+        .filter(|&point| is_solvable_without_guessing(state, point))
+        .collect()
+}
+
+// Groups of safe cells that stay closed after playing the first click out by
+// logic alone, so reaching any of them takes a guess
+pub fn isolated_safe_regions(state: &GameState, first_click: Point) -> Vec<Vec<Point>> {
+    let solved = solve_by_logic(state, first_click);
+    let grid = &solved.grid;
+    let is_closed_safe = |point: Point| {
+        matches!(
+            grid[point],
+            Cell {
+                cell_type: CellType::Empty { .. },
+                state: CellState::Unopened(_),
+            }
+        )
+    };
+
+    let mut seen = HashSet::new();
+    let mut regions = Vec::new();
+    for point in grid.points().filter(|&point| is_closed_safe(point)) {
+        if !seen.insert(point) {
+            continue;
+        }
+        let mut region = vec![point];
+        let mut pending = vec![point];
+        while let Some(next) = pending.pop() {
+            for &adj in grid.adjacent(next) {
+                if is_closed_safe(adj) && seen.insert(adj) {
+                    region.push(adj);
+                    pending.push(adj);
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
+// Number of groups of safe cells, opened or not, joined through any of the 8
+// neighbors the way cascades spread. More than one means mines wall some safe
+// cells off from the others. Walls are not safe cells, so a mask can split
+// the board too.
+pub fn safe_connectivity(state: &GameState) -> usize {
+    let grid = &state.grid;
+    let is_safe = |point: Point| {
+        matches!(grid[point].cell_type, CellType::Empty { .. }) && !grid.is_wall(point)
+    };
+
+    let mut seen = HashSet::new();
+    let mut components = 0;
+    for point in grid.points().filter(|&point| is_safe(point)) {
+        if !seen.insert(point) {
+            continue;
+        }
+        components += 1;
+        let mut pending = vec![point];
+        while let Some(next) = pending.pop() {
+            for &adj in grid.adjacent(next) {
+                if is_safe(adj) && seen.insert(adj) {
+                    pending.push(adj);
+                }
+            }
+        }
+    }
+    components
+}
+
+// The mines in groups tied together by numbers, two mines being in the same
+// group when some safe cell touches both of them, opened or not. Numbers of
+// one group never count mines of another, so each group is a part of the
+// layout that can be reasoned about on its own. Mines in row order within a
+// group, groups in row order of their first mine.
+pub fn mine_clusters(state: &GameState) -> Vec<Vec<Point>> {
+    let grid = &state.grid;
+    let is_mine = |point: Point| grid[point].cell_type == CellType::Mine;
+
+    let mut seen = HashSet::new();
+    let mut clusters = Vec::new();
+    for point in grid.points().filter(|&point| is_mine(point)) {
+        if !seen.insert(point) {
+            continue;
+        }
+        let mut cluster = vec![point];
+        let mut pending = vec![point];
+        while let Some(mine) = pending.pop() {
+            for &number in grid.adjacent(mine).iter().filter(|&&adj| !is_mine(adj)) {
+                for &other in grid.adjacent(number) {
+                    if is_mine(other) && seen.insert(other) {
+                        cluster.push(other);
+                        pending.push(other);
+                    }
+                }
+            }
+        }
+        cluster.sort_unstable_by_key(|&(x, y)| (y, x));
+        clusters.push(cluster);
+    }
+    clusters
+}
+
+// Minimum number of clicks that clears the board: one per opening (a region of
+// zeros together with its numbered border) plus one per number outside openings
+pub fn board_3bv(state: &GameState) -> u32 {
+    minimal_winning_clicks(state).len() as u32
+}
+
+// One zero per opening and every number outside the openings, which together
+// clear the board in exactly 3BV clicks
+pub fn minimal_winning_clicks(state: &GameState) -> Vec<Point> {
+    let grid = &state.grid;
+    let is_zero = |point: Point| {
+        matches!(grid[point].cell_type, CellType::Empty { adjacent_mines: 0 })
+            && !grid.is_wall(point)
+    };
+
+    let mut marked = HashSet::new();
+    let mut clicks = Vec::new();
+    for point in grid.points().filter(|&point| is_zero(point)) {
+        if marked.contains(&point) {
+            continue;
+        }
+        clicks.push(point);
+        marked.insert(point);
+        let mut pending = vec![point];
+        while let Some(zero) = pending.pop() {
+            for &adj in grid.adjacent(zero) {
+                if marked.insert(adj) && is_zero(adj) {
+                    pending.push(adj);
+                }
+            }
+        }
+    }
+
+    for point in grid.points().filter(|&point| !grid.is_wall(point)) {
+        if matches!(grid[point].cell_type, CellType::Empty { .. }) && !marked.contains(&point) {
+            clicks.push(point);
+        }
+    }
+    clicks
+}
+
+// The unflagged closed cell least likely to be a mine, with its chance of
+// being one. Ties, chances within rounding of each other, are broken by the
+// rng so the choice can be replayed.
+pub(crate) fn best_guess<R: Rng + ?Sized>(state: &GameState, rng: &mut R) -> Option<(Point, f64)> {
+    let probabilities = mine_probabilities(state);
+    let candidates = state
+        .grid
+        .points()
+        .filter(|&point| state.grid[point].state == CellState::Unopened(Flag::Unflagged))
+        .map(|point| (point, probabilities.get(&point).copied().unwrap_or(1.0)))
+        .collect::<Vec<(Point, f64)>>();
+    let lowest = candidates
+        .iter()
+        .map(|&(_, chance)| chance)
+        .min_by(f64::total_cmp)?;
+    let tied = candidates
+        .into_iter()
+        .filter(|&(_, chance)| chance - lowest < 1e-9)
+        .collect::<Vec<(Point, f64)>>();
+    tied.choose(rng).copied()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hint {
+    OpenSafe(Point),
+    FlagMine(Point),
+    // Nothing is proven, this is the cell least likely to be a mine and its
+    // chance of being one
+    BestGuess(Point, f64),
+}
+
+// One thing to do next: open a proven safe cell, else flag a proven mine
+// that isn't flagged yet, else take the best guess. None once the game is
+// over or nothing is left to click.
+pub fn next_hint(state: &GameState) -> Option<Hint> {
+    next_hint_with_rng(state, &mut rand::thread_rng())
+}
+
+// Same as next_hint, with the rng choosing between equally good guesses
+pub fn next_hint_with_rng<R: Rng + ?Sized>(state: &GameState, rng: &mut R) -> Option<Hint> {
+    if state.status != GameStatus::InProgress {
+        return None;
+    }
+
+    let (safe, mines) = deduce_all(state);
+    let first = |set: &HashSet<Point>| state.grid.points().find(|point| set.contains(point));
+    if let Some(point) = first(&safe) {
+        return Some(Hint::OpenSafe(point));
+    }
+    let unflagged = mines
+        .into_iter()
+        .filter(|&point| state.grid[point].state != CellState::Unopened(Flag::Sure))
+        .collect::<HashSet<Point>>();
+    if let Some(point) = first(&unflagged) {
+        return Some(Hint::FlagMine(point));
+    }
+    best_guess(state, rng).map(|(point, chance)| Hint::BestGuess(point, chance))
+}
+
+// The solver playing the board out on a copy, one line per step, for
+// snapshot tests that catch changes in its reasoning. It opens first_click
+// on a fresh board, then in rounds writes every new deduction in the order
+// deduce_all_explained finds them, flags the mines and opens the safe cells.
+// A round without any takes the best guess, ties broken by an rng seeded
+// with 0, so the trace of a board never changes. Player flags are ignored.
+pub fn solve_trace(state: &GameState, first_click: Point) -> String {
+    solve_trace_with_rng(state, first_click, &mut StdRng::seed_from_u64(0))
+}
+
+// Same as solve_trace with the rng breaking ties between guesses
+pub fn solve_trace_with_rng<R: Rng + ?Sized>(
+    state: &GameState,
+    first_click: Point,
+    rng: &mut R,
+) -> String {
+    let mut played = state.clone();
+    played.rules.max_opens = None;
+    played.rules.open_flag_policy = OpenFlagPolicy::OpenAll;
+    let mut trace = String::new();
+    let open = |played: GameState, point: Point, trace: &mut String| {
+        let before = played
+            .grid
+            .iter()
+            .filter(|cell| cell.state == CellState::Opened)
+            .count();
+        let played = open_cell(played, point);
+        let after = played
+            .grid
+            .iter()
+            .filter(|cell| cell.state == CellState::Opened)
+            .count();
+        trace.push_str(&format!(
+            "open ({}, {}): {} opened\n",
+            point.0,
+            point.1,
+            after - before
+        ));
+        played
+    };
+    if !played.grid.any_opened() {
+        played = open(played, first_click, &mut trace);
+    }
+
+    while played.status == GameStatus::InProgress {
+        let (_, _, deductions) = deduce_all_explained(&played);
+        let new = deductions
+            .into_iter()
+            .filter(|deduction| match deduction.mine {
+                true => played.grid[deduction.point].state != CellState::Unopened(Flag::Sure),
+                false => played.grid[deduction.point].state != CellState::Opened,
+            })
+            .collect::<Vec<Deduction>>();
+        if new.is_empty() {
+            let Some(((x, y), chance)) = best_guess(&played, rng) else {
+                break;
+            };
+            trace.push_str(&format!("guess ({}, {}) at {:.1}%\n", x, y, chance * 100.0));
+            played = open(played, (x, y), &mut trace);
+            continue;
+        }
+
+        for deduction in new.iter() {
+            trace.push_str(&format!("{}\n", deduction));
+        }
+        for deduction in new {
+            if deduction.mine {
+                played = change_flag(played, deduction.point, Flag::Sure);
+            } else if played.grid[deduction.point].state != CellState::Opened {
+                played = open(played, deduction.point, &mut trace);
+            }
+        }
+    }
+    trace.push_str(&format!("{:?}\n", played.status));
+    trace
+}
+
+// The cells clicking a closed safe cell would open, in the order they open.
+// The state is left alone, and mines and opened cells open nothing.
+pub fn cascade_cells(state: &GameState, point: Point) -> Vec<Point> {
+    match state.grid[point] {
+        Cell {
+            cell_type: CellType::Empty { .. },
+            state: CellState::Unopened(_),
+        } => open_iter(&mut state.clone(), point).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// How many cells clicking each closed safe cell would open, indexed [y][x].
+// Mines and opened cells report 0.
+pub fn cascade_sizes(state: &GameState) -> Vec<Vec<usize>> {
+    (0..state.grid.height())
+        .map(|y| {
+            (0..state.grid.width())
+                .map(|x| cascade_cells(state, (x, y)).len())
+                .collect()
+        })
+        .collect()
+}
+
+// The safe cell opening the most cells, the one nearest the center among
+// equals and the first in row order after that. A board with nothing left to
+// open suggests its center, and a board without cells (0, 0).
+pub fn best_first_move(state: &GameState) -> Point {
+    let (right, bottom) = (
+        state.grid.width().saturating_sub(1),
+        state.grid.height().saturating_sub(1),
+    );
+    // Squared distance to the center in half cells, so it stays whole
+    let off_center = |(x, y): Point| {
+        let dx = (2 * x).abs_diff(right);
+        let dy = (2 * y).abs_diff(bottom);
+        dx * dx + dy * dy
+    };
+    let sizes = cascade_sizes(state);
+    state
+        .grid
+        .points()
+        .filter(|&(x, y)| sizes[y][x] > 0)
+        .min_by_key(|&(x, y)| (Reverse(sizes[y][x]), off_center((x, y)), y, x))
+        .unwrap_or((right / 2, bottom / 2))
+}
+
+// The closed zero whose cascade would open the most cells, the first in row
+// order among equals. Every zero of an empty region opens the same cells, so
+// this is a cell of the biggest region still closed. None once no closed
+// zero is left.
+pub fn largest_remaining_cascade(state: &GameState) -> Option<Point> {
+    let grid = &state.grid;
+    grid.points()
+        .filter(|&point| {
+            grid[point].cell_type == CellType::Empty { adjacent_mines: 0 }
+                && grid[point].state != CellState::Opened
+                && state.rules.open_flag_policy.opens(grid[point].state)
+        })
+        .map(|point| (point, cascade_cells(state, point).len()))
+        .min_by_key(|&((x, y), size)| (Reverse(size), y, x))
+        .map(|(point, _)| point)
+}
+
+// Frontier cells tied together by shared numbers, with the numbers that
+// constrain them. Cells are referred to by their index in cells.
+struct Component {
+    cells: Vec<Point>,
+    constraints: Vec<(Vec<usize>, usize)>,
+}
+
+// Splits the constraints into groups sharing no cells, in row order of their
+// first cell
+fn split_components(constraints: &[(Vec<Point>, usize)]) -> Vec<Component> {
+    // Union-find over constraints, joined whenever they share a cell
+    let mut parent = (0..constraints.len()).collect::<Vec<usize>>();
+    fn root(parent: &mut [usize], index: usize) -> usize {
+        let mut index = index;
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+    let mut first_seen: HashMap<Point, usize> = HashMap::new();
+    for (index, (cells, _)) in constraints.iter().enumerate() {
+        for &cell in cells.iter() {
+            let other = *first_seen.entry(cell).or_insert(index);
+            let (a, b) = (root(&mut parent, index), root(&mut parent, other));
+            parent[a] = b;
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for index in 0..constraints.len() {
+        let group = *group_of.entry(root(&mut parent, index)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+
+    let mut components = groups
+        .into_iter()
+        .map(|group| {
+            let mut cells = group
+                .iter()
+                .flat_map(|&index| constraints[index].0.iter().copied())
+                .collect::<Vec<Point>>();
+            cells.sort_unstable_by_key(|&(x, y)| (y, x));
+            cells.dedup();
+            let index_of = cells
+                .iter()
+                .enumerate()
+                .map(|(index, &cell)| (cell, index))
+                .collect::<HashMap<Point, usize>>();
+            let constraints = group
+                .iter()
+                .map(|&index| {
+                    let (cells, count) = &constraints[index];
+                    (cells.iter().map(|cell| index_of[cell]).collect(), *count)
+                })
+                .collect();
+            Component { cells, constraints }
+        })
+        .collect::<Vec<Component>>();
+    components.sort_unstable_by_key(|component| {
+        let (x, y) = component.cells[0];
+        (y, x)
+    });
+    components
+}
+
+// Groups of frontier cells the numbers tie together, sharing no number with
+// any other group, so each can be solved on its own. Cells in row order
+// within a group, groups in row order of their first cell.
+pub fn independent_components(state: &GameState) -> Vec<Vec<Point>> {
+    let mut groups = split_components(&constraints(state))
+        .into_iter()
+        .map(|component| {
+            let mut cells = component.cells;
+            cells.sort_unstable_by_key(|&(x, y)| (y, x));
+            cells
+        })
+        .collect::<Vec<Vec<Point>>>();
+    groups.sort_unstable_by_key(|cells| {
+        let (x, y) = cells[0];
+        (y, x)
+    });
+    groups
+}
+
+// Frontier size from which components are solved on several threads, below
+// it spawning them costs more than it saves
+const PARALLEL_CELLS: usize = 48;
+
+// Solves every component, in parallel when the frontier is big enough. The
+// results are in the order of the components either way.
+fn solve_components(
+    components: &[Component],
+    deadline: Option<Instant>,
+) -> Vec<Option<ComponentSolutions>> {
+    let cells = components
+        .iter()
+        .map(|component| component.cells.len())
+        .sum::<usize>();
+    let workers = thread::available_parallelism().map_or(1, |workers| workers.get());
+    let workers = min(workers, components.len());
+    if workers < 2 || cells < PARALLEL_CELLS {
+        return components
+            .iter()
+            .map(|component| solve_component(component, deadline))
+            .collect();
+    }
+
+    // Worker w takes components w, w + workers and so on, which spreads the
+    // big ones around better than handing out runs of neighbors
+    let mut solved = thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    (worker..components.len())
+                        .step_by(workers)
+                        .map(|index| (index, solve_component(&components[index], deadline)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("solver thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    solved.sort_unstable_by_key(|&(index, _)| index);
+    solved.into_iter().map(|(_, solutions)| solutions).collect()
+}
+
+// Solutions of a component by how many mines they place, and how often each
+// cell is a mine among them
+struct ComponentSolutions {
+    counts: Vec<f64>,
+    cell_counts: Vec<Vec<f64>>,
+}
+
+// Backtracking over a component's cells in order, pruning as soon as a number
+// can no longer be met
+struct Search<'a> {
+    component: &'a Component,
+    touching: Vec<Vec<usize>>,
+    assignment: Vec<bool>,
+    // Mines placed and cells still unassigned around every number
+    placed: Vec<usize>,
+    unassigned: Vec<usize>,
+    solutions: ComponentSolutions,
+    // Given up once this passes, checked every few thousand steps
+    deadline: Option<Instant>,
+    steps: u32,
+    timed_out: bool,
+}
+
+impl Search<'_> {
+    fn run(&mut self, cell: usize, mines: usize) {
+        self.steps = self.steps.wrapping_add(1);
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(4096) && Instant::now() > deadline {
+                self.timed_out = true;
+            }
+        }
+        if self.timed_out {
+            return;
+        }
+        if cell == self.assignment.len() {
+            self.solutions.counts[mines] += 1.0;
+            for (index, &mine) in self.assignment.iter().enumerate() {
+                if mine {
+                    self.solutions.cell_counts[mines][index] += 1.0;
+                }
+            }
+            return;
+        }
+
+        for mine in [false, true] {
+            self.assignment[cell] = mine;
+            let mut fits = true;
+            for &index in self.touching[cell].iter() {
+                self.unassigned[index] -= 1;
+                self.placed[index] += usize::from(mine);
+                let target = self.component.constraints[index].1;
+                if self.placed[index] > target
+                    || self.placed[index] + self.unassigned[index] < target
+                {
+                    fits = false;
+                }
+            }
+            if fits {
+                self.run(cell + 1, mines + usize::from(mine));
+            }
+            for &index in self.touching[cell].iter() {
+                self.unassigned[index] += 1;
+                self.placed[index] -= usize::from(mine);
+            }
+        }
+    }
+}
+
+// None when the deadline passes before every solution is counted
+fn solve_component(component: &Component, deadline: Option<Instant>) -> Option<ComponentSolutions> {
+    let size = component.cells.len();
+    let mut touching = vec![Vec::new(); size];
+    for (index, (cells, _)) in component.constraints.iter().enumerate() {
+        for &cell in cells.iter() {
+            touching[cell].push(index);
+        }
+    }
+
+    let mut search = Search {
+        component,
+        touching,
+        assignment: vec![false; size],
+        placed: vec![0; component.constraints.len()],
+        unassigned: component
+            .constraints
+            .iter()
+            .map(|(cells, _)| cells.len())
+            .collect(),
+        solutions: ComponentSolutions {
+            counts: vec![0.0; size + 1],
+            cell_counts: vec![vec![0.0; size]; size + 1],
+        },
+        deadline,
+        steps: 0,
+        timed_out: false,
+    };
+    search.run(0, 0);
+    if search.timed_out {
+        None
+    } else {
+        Some(search.solutions)
+    }
+}
+
+fn ln_choose(ln_factorials: &[f64], n: usize, k: usize) -> Option<f64> {
+    if k > n {
+        None
+    } else {
+        Some(ln_factorials[n] - ln_factorials[k] - ln_factorials[n - k])
+    }
+}
+
+// Exact chance of each closed cell being a mine given the opened numbers and
+// the total mine count, treating every consistent layout as equally likely.
+// Flags are ignored. An inconsistent board gives an empty map.
+pub fn mine_probabilities(state: &GameState) -> HashMap<Point, f64> {
+    combine_probabilities(state, None)
+}
+
+// Same as mine_probabilities, but a group of frontier cells the exact count
+// can't finish within the budget gets a local estimate instead: the share of
+// mines left around each of its numbers, averaged over the numbers next to
+// the cell. While weighing the other cells such a group is treated like the
+// cells with no number next to them.
+pub fn mine_probabilities_budgeted(state: &GameState, budget: Duration) -> HashMap<Point, f64> {
+    combine_probabilities(state, Some(Instant::now() + budget))
+}
+
+// Every closed cell the numbers and the mine count pin down, true for a mine
+// and false for a safe cell. Built on the exact probabilities, so it finds
+// everything deduce_all does and also what only the mine count settles.
+pub fn determined_cells(state: &GameState) -> HashMap<Point, bool> {
+    mine_probabilities(state)
+        .into_iter()
+        .filter(|&(point, _)| state.grid[point].state != CellState::Opened)
+        .filter_map(|(point, p)| match p {
+            p if p < 1e-9 => Some((point, false)),
+            p if p > 1.0 - 1e-9 => Some((point, true)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Share of the closed cells next to opened numbers that are settled, either
+// pinned down by determined_cells or given a Sure flag by the player. Near
+// 1.0 the deductions along the revealed area are nearly done. With no such
+// cells there is nothing left to settle, which counts as 1.0.
+pub fn border_completeness(state: &GameState) -> f64 {
+    let grid = &state.grid;
+    let border = grid
+        .points()
+        .filter(|&point| matches!(grid[point].state, CellState::Unopened(_)))
+        .filter(|&point| {
+            grid.adjacent(point).iter().any(|&adj| {
+                grid[adj].state == CellState::Opened
+                    && matches!(
+                        grid[adj].cell_type,
+                        CellType::Empty {
+                            adjacent_mines: 1..
+                        }
+                    )
+            })
+        })
+        .collect::<Vec<Point>>();
+    if border.is_empty() {
+        return 1.0;
+    }
+    let determined = determined_cells(state);
+    let settled = border
+        .iter()
+        .filter(|&point| {
+            determined.contains_key(point) || grid[*point].state == CellState::Unopened(Flag::Sure)
+        })
+        .count();
+    settled as f64 / border.len() as f64
+}
+
+fn combine_probabilities(state: &GameState, deadline: Option<Instant>) -> HashMap<Point, f64> {
+    let grid = &state.grid;
+    let constraints = constraints(state);
+    let mut components = Vec::new();
+    let mut solutions = Vec::new();
+    let mut estimates = HashMap::new();
+    let split = split_components(&constraints);
+    let solved = solve_components(&split, deadline);
+    for (component, solved) in split.into_iter().zip(solved) {
+        match solved {
+            Some(component_solutions) => {
+                components.push(component);
+                solutions.push(component_solutions);
+            }
+            None => {
+                let mut shares = vec![Vec::new(); component.cells.len()];
+                for (cells, mines) in component.constraints.iter() {
+                    for &cell in cells.iter() {
+                        shares[cell].push(*mines as f64 / cells.len() as f64);
+                    }
+                }
+                for (&cell, shares) in component.cells.iter().zip(shares.iter()) {
+                    let estimate = shares.iter().sum::<f64>() / shares.len() as f64;
+                    estimates.insert(cell, estimate.clamp(0.0, 1.0));
+                }
+            }
+        }
+    }
+
+    let frontier = components
+        .iter()
+        .flat_map(|component| component.cells.iter().copied())
+        .collect::<HashSet<Point>>();
+    let unconstrained = grid
+        .points()
+        .filter(|&point| matches!(grid[point].state, CellState::Unopened(_)))
+        .filter(|point| !frontier.contains(point))
+        .collect::<Vec<Point>>();
+    let interior = unconstrained
+        .iter()
+        .copied()
+        .filter(|point| !estimates.contains_key(point))
+        .collect::<Vec<Point>>();
+    let opened_mines = grid
+        .iter()
+        .filter(|cell| cell.cell_type == CellType::Mine && cell.state == CellState::Opened)
+        .count();
+    let mines_left = state.mine_positions().len() - opened_mines;
+
+    // Relative weight of the interior holding whatever the frontier leaves over
+    let ln_factorials = (0..=unconstrained.len())
+        .scan(0.0, |sum, n| {
+            if n > 0 {
+                *sum += (n as f64).ln();
+            }
+            Some(*sum)
+        })
+        .collect::<Vec<f64>>();
+    let ln_weights = (0..=frontier.len())
+        .map(|frontier_mines| {
+            mines_left
+                .checked_sub(frontier_mines)
+                .and_then(|rest| ln_choose(&ln_factorials, unconstrained.len(), rest))
+        })
+        .collect::<Vec<Option<f64>>>();
+    let Some(max_ln_weight) = ln_weights.iter().flatten().copied().reduce(f64::max) else {
+        return HashMap::new();
+    };
+    let weights = ln_weights
+        .iter()
+        .map(|ln_weight| ln_weight.map_or(0.0, |ln_weight| (ln_weight - max_ln_weight).exp()))
+        .collect::<Vec<f64>>();
+
+    // Number of solutions of all components but one, by mine count
+    let convolve = |skip: Option<usize>| {
+        solutions
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| Some(index) != skip)
+            .fold(vec![1.0], |total, (_, component)| {
+                let mut next = vec![0.0; total.len() + component.counts.len() - 1];
+                for (a, &left) in total.iter().enumerate() {
+                    for (b, &right) in component.counts.iter().enumerate() {
+                        next[a + b] += left * right;
+                    }
+                }
+                next
+            })
+    };
+
+    let all = convolve(None);
+    let total = all
+        .iter()
+        .enumerate()
+        .map(|(mines, &count)| count * weights[mines])
+        .sum::<f64>();
+    if total == 0.0 {
+        return HashMap::new();
+    }
+
+    let mut probabilities = HashMap::new();
+    for (index, (component, component_solutions)) in
+        components.iter().zip(solutions.iter()).enumerate()
+    {
+        let rest = convolve(Some(index));
+        for (cell_index, &cell) in component.cells.iter().enumerate() {
+            let mut weight = 0.0;
+            for (mines, cell_counts) in component_solutions.cell_counts.iter().enumerate() {
+                for (rest_mines, &rest_count) in rest.iter().enumerate() {
+                    weight += cell_counts[cell_index] * rest_count * weights[mines + rest_mines];
+                }
+            }
+            probabilities.insert(cell, weight / total);
+        }
+    }
+
+    if !interior.is_empty() {
+        let expected = all
+            .iter()
+            .enumerate()
+            .map(|(mines, &count)| count * weights[mines] * mines_left.saturating_sub(mines) as f64)
+            .sum::<f64>()
+            / total;
+        let probability = expected / unconstrained.len() as f64;
+        for &point in interior.iter() {
+            probabilities.insert(point, probability);
+        }
+    }
+    probabilities.extend(estimates);
+    probabilities
+}
+
+// Chances of a mine for the frontier cells within radius of a point, counting
+// rings like the 8 neighbors do, cheap enough to run on every hover. Only the
+// numbers next to those cells are solved, every solution of them counting
+// the same. Numbers further out and the total mine count are left out, so
+// near the edge of the window the chances can differ from
+// mine_probabilities.
+pub fn local_probability(state: &GameState, around: Point, radius: usize) -> HashMap<Point, f64> {
+    let within = |(x, y): Point, reach: usize| {
+        x.abs_diff(around.0) <= reach && y.abs_diff(around.1) <= reach
+    };
+    let constraints = numbered_constraints(state)
+        .into_iter()
+        .filter(|&(number, _, _)| within(number, radius + 1))
+        .map(|(_, closed, mines)| (closed, mines))
+        .collect::<Vec<(Vec<Point>, usize)>>();
+    let wanted = state
+        .frontier()
+        .into_iter()
+        .filter(|&point| within(point, radius))
+        .collect::<HashSet<Point>>();
+
+    let mut probabilities = HashMap::new();
+    for component in split_components(&constraints) {
+        let Some(solutions) = solve_component(&component, None) else {
+            continue;
+        };
+        let total = solutions.counts.iter().sum::<f64>();
+        if total == 0.0 {
+            continue;
+        }
+        for (index, &cell) in component.cells.iter().enumerate() {
+            if wanted.contains(&cell) {
+                let mines = solutions
+                    .cell_counts
+                    .iter()
+                    .map(|counts| counts[index])
+                    .sum::<f64>();
+                probabilities.insert(cell, mines / total);
+            }
+        }
+    }
+    probabilities
+}
+
+// Chance of a mine per cell for a heatmap, indexed [y][x]. Opened and flagged
+// cells have none.
+pub fn probability_grid(state: &GameState) -> Vec<Vec<Option<f64>>> {
+    let probabilities = mine_probabilities(state);
+    (0..state.grid.height())
+        .map(|y| {
+            (0..state.grid.width())
+                .map(|x| match state.grid[(x, y)].state {
+                    CellState::Unopened(Flag::Unflagged) => probabilities.get(&(x, y)).copied(),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Uncertainty left on the board in bits: the binary entropy of every closed
+// cell's mine probability, summed. 0 once every closed cell is known and one
+// bit for each cell that is a coin flip. Like the probabilities it ignores
+// how cells depend on each other, so it overstates the bits a full solution
+// still needs.
+pub fn remaining_entropy(state: &GameState) -> f64 {
+    mine_probabilities(state)
+        .values()
+        .filter(|&&p| p > 0.0 && p < 1.0)
+        .map(|&p| -(p * p.log2() + (1.0 - p) * (1.0 - p).log2()))
+        // Summing from 0 rather than -0 so a solved board doesn't show -0
+        .fold(0.0, |total, bits| total + bits)
+}
+
+// Where a guess can't be avoided. The board is first played out on a copy,
+// opening every cell the exact probabilities prove safe for as long as there
+// are any. What is left undecided is split into groups the numbers tie
+// together, and the smallest is returned in row order: a 50/50 gives its two
+// cells. With no numbers left to go on every closed cell is a guess. Empty
+// once logic alone clears the board.
+pub fn required_guesses(state: &GameState) -> Vec<Point> {
+    let mut played = state.clone();
+    played.rules.max_opens = None;
+    played.rules.open_flag_policy = OpenFlagPolicy::OpenAll;
+    let probabilities = loop {
+        if played.status != GameStatus::InProgress {
+            return Vec::new();
+        }
+        let probabilities = mine_probabilities(&played);
+        let safe = probabilities
+            .iter()
+            .filter(|&(_, &p)| p < 1e-9)
+            .map(|(&point, _)| point)
+            .filter(|&point| played.grid[point].state != CellState::Opened)
+            .collect::<Vec<Point>>();
+        if safe.is_empty() {
+            break probabilities;
+        }
+        played = safe.into_iter().fold(played, open_cell);
+    };
+
+    let undecided = |point: &Point| {
+        probabilities
+            .get(point)
+            .is_some_and(|&p| p > 1e-9 && p < 1.0 - 1e-9)
+    };
+    let smallest = split_components(&constraints(&played))
+        .into_iter()
+        .map(|component| {
+            component
+                .cells
+                .into_iter()
+                .filter(undecided)
+                .collect::<Vec<Point>>()
+        })
+        .filter(|cells| !cells.is_empty())
+        .min_by_key(|cells| cells.len());
+    match smallest {
+        Some(cells) => cells,
+        None => played.grid.points().filter(undecided).collect(),
+    }
+}
+
+// Rough count of moves left before the board is done: what can be deduced
+// right now plus one move for each cell of the next unavoidable guess. Only
+// an estimate, as a guess that turns out well usually opens more to deduce.
+pub fn estimated_steps_remaining(state: &GameState) -> usize {
+    if state.status != GameStatus::InProgress {
+        return 0;
+    }
+    remaining_logical_moves(state) + required_guesses(state).len()
+}
+
+// Every mine, in row order, when the opened numbers and the mine count leave
+// only one possible layout. Meant for boards imported from elsewhere, where
+// the layout behind the closed cells isn't trusted.
+pub fn deduce_mine_positions(state: &GameState) -> Option<Vec<Point>> {
+    let probabilities = mine_probabilities(state);
+    let grid = &state.grid;
+    if probabilities.is_empty() && grid.iter().any(|cell| cell.state != CellState::Opened) {
+        return None;
+    }
+
+    let mut mines = Vec::new();
+    for point in grid.points() {
+        match (grid[point].state, probabilities.get(&point)) {
+            (CellState::Opened, _) => {
+                if let CellType::Mine = grid[point].cell_type {
+                    mines.push(point);
+                }
+            }
+            (_, Some(&p)) if p > 1.0 - 1e-9 => mines.push(point),
+            (_, Some(&p)) if p < 1e-9 => (),
+            _ => return None,
+        }
+    }
+    Some(mines)
+}
+
+// Hint score for each frontier cell: the chance it is safe times roughly how
+// many cells opening it reveals. A safe cell reveals itself, and if it is a
+// zero it also reveals its closed unflagged neighbors. The chance of a zero is
+// taken as every closed neighbor being safe independently, which ignores how
+// the neighbors constrain each other and what a cascade reaches beyond them.
+pub fn expected_open_value(state: &GameState) -> HashMap<Point, f64> {
+    let grid = &state.grid;
+    let probabilities = mine_probabilities(state);
+    if probabilities.is_empty() {
+        return HashMap::new();
+    }
+
+    state
+        .frontier()
+        .into_iter()
+        .map(|point| {
+            let adj = grid.adjacent(point);
+            let revealed = adj
+                .iter()
+                .filter(|&&adj| matches!(grid[adj].state, CellState::Unopened(Flag::Unflagged)))
+                .count();
+            // Flags are ignored like in the probabilities, but a mine already
+            // opened next to the cell rules out a zero
+            let zero = adj
+                .iter()
+                .map(|adj| match grid[*adj].state {
+                    CellState::Unopened(_) => 1.0 - probabilities[adj],
+                    CellState::Opened => match grid[*adj].cell_type {
+                        CellType::Mine => 0.0,
+                        CellType::Empty { .. } => 1.0,
+                    },
+                })
+                .product::<f64>();
+            let safe = 1.0 - probabilities[&point];
+            (point, safe * (1.0 + zero * revealed as f64))
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+use rusty_mines::*;
+
+const SEEDS: u64 = 10;
+
+// Plays every preset to the end with the solver. auto_play checks the state
+// with debug_validate before each move in debug builds, and the final state
+// is checked here.
+#[test]
+fn auto_play_finishes_every_preset() {
+    for difficulty in [
+        Difficulty::Beginner,
+        Difficulty::Intermediate,
+        Difficulty::Expert,
+    ] {
+        let (width, height, _) = difficulty.params();
+        for seed in 0..SEEDS {
+            let mut game = Game::from_difficulty(difficulty, Some(seed));
+            auto_play(&mut game, (width / 2, height / 2));
+
+            assert_ne!(
+                game.state.status,
+                GameStatus::InProgress,
+                "{} seed {} did not finish",
+                difficulty,
+                seed
+            );
+            assert_eq!(
+                debug_validate(&game.state),
+                Ok(()),
+                "{} seed {}",
+                difficulty,
+                seed
+            );
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use rusty_mines::*;
+
+const CLOSED_ZERO: Cell = Cell {
+    cell_type: CellType::Empty { adjacent_mines: 0 },
+    state: CellState::Unopened(Flag::Unflagged),
+};
+
+#[test]
+fn empty_board_has_only_closed_zeros() {
+    let state = GameState::empty(3, 3);
+    assert_eq!((state.grid.width(), state.grid.height()), (3, 3));
+    assert!(state.grid.iter().all(|&cell| cell == CLOSED_ZERO));
+    assert_eq!(state.status, GameStatus::InProgress);
+}
+
+#[test]
+fn empty_board_is_won_by_opening_its_cells() {
+    let mut state = GameState::empty(3, 3);
+    for point in state.grid.points().collect::<Vec<Point>>() {
+        state = open_cell(state, point);
+    }
+    assert_eq!(state.status, GameStatus::Won);
+    assert!(state
+        .grid
+        .iter()
+        .all(|cell| cell.state == CellState::Opened));
+}
+
+#[test]
+fn filled_board_repeats_the_cell() {
+    let flagged = Cell {
+        cell_type: CellType::Empty { adjacent_mines: 0 },
+        state: CellState::Unopened(Flag::Sure),
+    };
+    let state = GameState::filled(4, 2, flagged);
+    assert_eq!(state.grid.iter().count(), 8);
+    assert!(state.grid.iter().all(|&cell| cell == flagged));
+}
+
+#[test]
+fn grid_indexes_by_point() {
+    let mut grid = Grid::new(3, 2, CLOSED_ZERO);
+    grid[(2, 1)].state = CellState::Opened;
+    assert_eq!(grid[(2, 1)].state, CellState::Opened);
+    assert_eq!(grid[(1, 1)], CLOSED_ZERO);
+    assert_eq!((grid.width(), grid.height()), (3, 2));
+}
+
+#[test]
+fn grid_bounds_end_at_the_last_row_and_column() {
+    let grid = Grid::new(3, 2, CLOSED_ZERO);
+    assert!(grid.in_bounds((0, 0)));
+    assert!(grid.in_bounds((2, 1)));
+    assert!(!grid.in_bounds((3, 0)));
+    assert!(!grid.in_bounds((0, 2)));
+}
+
+#[test]
+fn grid_get_is_none_out_of_bounds() {
+    let mut grid = Grid::new(3, 2, CLOSED_ZERO);
+    assert_eq!(grid.get((2, 1)), Some(&CLOSED_ZERO));
+    assert_eq!(grid.get((3, 1)), None);
+    assert_eq!(grid.get((0, 5)), None);
+    assert!(grid.get_mut((4, 4)).is_none());
+    if let Some(cell) = grid.get_mut((0, 1)) {
+        cell.state = CellState::Unopened(Flag::Unsure);
+    }
+    assert_eq!(grid[(0, 1)].state, CellState::Unopened(Flag::Unsure));
+}
+
+#[test]
+#[should_panic]
+fn grid_index_out_of_bounds_panics() {
+    let grid = Grid::new(3, 2, CLOSED_ZERO);
+    let _ = grid[(3, 0)];
+}
+
+#[test]
+fn observation_never_shows_a_closed_mine() {
+    // * 1 .
+    // 1 1 .
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    assert_eq!(
+        state.observation(),
+        vec![vec![-1, -1, -1], vec![-1, -1, -1]]
+    );
+
+    let state = change_flag(state, (1, 0), Flag::Unsure);
+    let state = open_cell(state, (1, 1));
+    assert_eq!(state.observation(), vec![vec![-1, -2, -1], vec![-1, 1, -1]]);
+}
+
+#[test]
+fn cached_neighbors_match_find_adjacent() {
+    for (width, height) in [(1, 1), (1, 5), (4, 3), (9, 9)] {
+        let grid = GameState::empty(width, height).grid;
+        for (x, y) in grid.points() {
+            assert_eq!(
+                grid.adjacent((x, y)),
+                find_adjacent(x, y, width, height),
+                "{}x{} at ({}, {})",
+                width,
+                height,
+                x,
+                y
+            );
+        }
+    }
+}
+
+#[test]
+fn frontier_is_the_closed_cells_next_to_opened_numbers() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    assert_eq!(state.frontier(), Vec::new());
+
+    let state = open_cell(state, (3, 0));
+    assert_eq!(
+        state.frontier(),
+        vec![(1, 0), (1, 1), (1, 2), (1, 3), (2, 3), (3, 3)]
+    );
+    // Flagged cells are settled and leave the frontier
+    let state = change_flag(state, (1, 1), Flag::Sure);
+    assert_eq!(
+        state.frontier(),
+        vec![(1, 0), (1, 2), (1, 3), (2, 3), (3, 3)]
+    );
+}
+
+#[test]
+fn a_fresh_beginner_board_has_71_safe_cells_left() {
+    let (width, height, mines) = Difficulty::Beginner.params();
+    let state = initial_state_seeded(width, height, mines, 5);
+    assert_eq!(state.total_cells(), 81);
+    assert_eq!(state.safe_cells_remaining(), 71);
+
+    let click = state
+        .grid
+        .points()
+        .find(|&point| state.grid[point].cell_type != CellType::Mine)
+        .unwrap();
+    let state = open_cell(state, click);
+    let opened = state
+        .grid
+        .iter()
+        .filter(|cell| cell.state == CellState::Opened)
+        .count();
+    assert_eq!(state.safe_cells_remaining(), 71 - opened);
+    assert_eq!(state.total_cells(), 81);
+}
+
+#[test]
+fn fully_marked_means_exactly_the_mines_are_flagged() {
+    let state = GameState::from_mines(3, 3, &[(0, 0), (2, 2)]);
+    assert!(!state.is_fully_marked());
+
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = change_flag(state, (2, 2), Flag::Sure);
+    // Unsure flags are no claim either way
+    let state = change_flag(state, (1, 1), Flag::Unsure);
+    assert!(state.is_fully_marked());
+    assert_eq!(state.status, GameStatus::InProgress);
+
+    let state = change_flag(state, (2, 0), Flag::Sure);
+    assert!(!state.is_fully_marked());
+}
+
+#[test]
+fn open_order_counts_up_in_the_order_cells_open() {
+    let mut state = initial_state_seeded(9, 9, 10, 6);
+    let zero = state
+        .grid
+        .points()
+        .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        .unwrap();
+    let mut opened = open_iter(&mut state, zero).collect::<Vec<Point>>();
+    let next = state
+        .grid
+        .points()
+        .find(|&point| {
+            state.grid[point].state == CellState::Unopened(Flag::Unflagged)
+                && state.grid[point].cell_type != CellType::Mine
+        })
+        .unwrap();
+    let state = open_cell(state, next);
+    opened.push(next);
+
+    for (tick, &point) in opened.iter().enumerate() {
+        assert_eq!(
+            state.grid.open_order(point),
+            Some(tick as u32),
+            "{:?}",
+            point
+        );
+    }
+    assert_eq!(
+        state
+            .grid
+            .points()
+            .filter(|&point| state.grid.open_order(point).is_some())
+            .count(),
+        opened.len()
+    );
+}
+
+#[test]
+fn a_mine_listed_twice_is_one_mine() {
+    let state = GameState::from_mines(3, 3, &[(0, 0), (0, 0)]);
+    assert_eq!(state.mine_positions(), vec![(0, 0)]);
+    assert_eq!(
+        state.grid[(1, 1)].cell_type,
+        CellType::Empty { adjacent_mines: 1 }
+    );
+    assert_eq!(state, GameState::from_mines(3, 3, &[(0, 0)]));
+}
+
+#[test]
+fn view_region_is_the_clipped_window_of_board_view() {
+    let state = initial_state_seeded(9, 6, 8, 4);
+    let state = open_cell(state, (4, 3));
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    let full = state.board_view();
+
+    let window = state.view_region((2, 1), (5, 3));
+    assert_eq!((window.len(), window[0].len()), (3, 4));
+    for (row, y) in window.iter().zip(1..) {
+        assert_eq!(row[..], full[y][2..6]);
+    }
+
+    // Hanging off the bottom right corner
+    let window = state.view_region((7, 4), (20, 20));
+    assert_eq!((window.len(), window[0].len()), (2, 2));
+    assert_eq!(window[1][..], full[5][7..]);
+    assert_eq!(
+        state.view_region((9, 0), (12, 3)),
+        Vec::<Vec<CellView>>::new()
+    );
+    assert_eq!(state.view_region((0, 0), (8, 5)), full);
+}
+
+#[test]
+fn four_quarter_turns_give_the_board_back() {
+    let state = initial_state_seeded(7, 4, 6, 8);
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    let state = open_cell(state, (3, 2));
+    let turned = state.rotate90();
+    assert_eq!((turned.grid.width(), turned.grid.height()), (4, 7));
+    assert_eq!(turned.grid[(3, 0)], state.grid[(0, 0)]);
+
+    let back = turned.rotate90().rotate90().rotate90();
+    for point in state.grid.points() {
+        assert_eq!(back.grid[point], state.grid[point], "{:?}", point);
+    }
+    assert_eq!(back.status, state.status);
+    assert_eq!(back.mine_positions(), state.mine_positions());
+}
+
+#[test]
+fn flips_keep_every_number_right() {
+    let state = initial_state_seeded(7, 4, 6, 8);
+    for flipped in [state.flip_horizontal(), state.flip_vertical()] {
+        for point in flipped.grid.points() {
+            if let CellType::Empty { adjacent_mines } = flipped.grid[point].cell_type {
+                assert_eq!(compute_cell_adjacency(&flipped, point), adjacent_mines);
+            }
+        }
+        assert_eq!(flipped.mine_positions().len(), 6);
+    }
+    assert_eq!(state.flip_horizontal().grid[(6, 1)], state.grid[(0, 1)]);
+    assert_eq!(state.flip_vertical().grid[(2, 3)], state.grid[(2, 0)]);
+    assert_eq!(state.flip_horizontal().flip_horizontal(), state);
+}
+
+#[test]
+fn one_click_clears_a_sandbox() {
+    let state = GameState::sandbox(6, 4);
+    assert_eq!(state.mine_positions(), Vec::new());
+    let state = open_cell(state, (5, 3));
+    assert_eq!(state.status, GameStatus::Won);
+    assert!(state
+        .grid
+        .iter()
+        .all(|cell| cell.state == CellState::Opened));
+}
+
+#[test]
+fn risky_neighbors_leaves_out_opened_and_sure_cells() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    let state = change_flag(state, (1, 1), Flag::Sure);
+    let state = change_flag(state, (1, 2), Flag::Unsure);
+    assert_eq!(state.risky_neighbors((2, 1)), vec![(1, 0), (1, 2)]);
+
+    // Only opened numbers have any
+    assert_eq!(state.risky_neighbors((3, 0)), Vec::new());
+    assert_eq!(state.risky_neighbors((1, 0)), Vec::new());
+}
+
+#[test]
+fn neighbor_summary_buckets_an_edge_cell_by_state() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    let state = change_flag(state, (3, 3), Flag::Sure);
+    let state = change_flag(state, (1, 2), Flag::Unsure);
+    let summary = state.neighbor_summary((2, 3));
+    assert_eq!(
+        summary,
+        NeighborSummary {
+            opened: vec![(2, 2), (3, 2)],
+            unopened: vec![(1, 3)],
+            sure: vec![(3, 3)],
+            unsure: vec![(1, 2)],
+        }
+    );
+    assert_eq!(summary.closed(), 3);
+}
+
+#[test]
+fn every_cell_round_trips_through_a_byte() {
+    let types = (0..=8)
+        .map(|adjacent_mines| CellType::Empty { adjacent_mines })
+        .chain([CellType::Mine]);
+    let mut bytes = std::collections::HashSet::new();
+    for cell_type in types {
+        for state in [
+            CellState::Opened,
+            CellState::Unopened(Flag::Unflagged),
+            CellState::Unopened(Flag::Unsure),
+            CellState::Unopened(Flag::Sure),
+        ] {
+            let cell = Cell { cell_type, state };
+            let packed = PackedCell::from(cell);
+            assert_eq!(packed.cell(), cell);
+            assert_eq!(packed.is_mine(), cell_type == CellType::Mine);
+            bytes.insert(packed.byte());
+        }
+    }
+    assert_eq!(bytes.len(), 40);
+}
+
+#[test]
+fn a_packed_grid_unpacks_to_the_same_cells() {
+    let state = initial_state_seeded(9, 7, 12, 3);
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = change_flag(state, (8, 6), Flag::Unsure);
+    let state = open_cell(state, (4, 3));
+    let packed = state.grid.pack();
+    assert_eq!((packed.width(), packed.height()), (9, 7));
+    assert_eq!(packed.bytes().count(), 63);
+
+    let unpacked = packed.unpack();
+    for point in state.grid.points() {
+        assert_eq!(unpacked[point], state.grid[point], "{:?}", point);
+        assert_eq!(packed.get(point), Some(state.grid[point]));
+    }
+    assert_eq!(packed.get((9, 0)), None);
+}
+
+#[test]
+fn walls_have_no_neighbors_and_are_nobodys_neighbor() {
+    let mask = vec![vec![true, true, true], vec![true, false, true]];
+    let grid = GameState::from_mines_masked(3, 2, &[], Some(mask))
+        .unwrap()
+        .grid;
+    assert_eq!(grid.adjacent((1, 1)), &[]);
+    assert_eq!(grid.adjacent((0, 0)), &[(0, 1), (1, 0)]);
+    assert!(grid
+        .points()
+        .all(|point| !grid.adjacent(point).contains(&(1, 1))));
+}
+
+#[test]
+fn masked_mines_must_be_on_the_board_and_off_the_walls() {
+    let mask = || Some(vec![vec![true, false], vec![true, true]]);
+    assert_eq!(
+        GameState::from_mines_masked(2, 2, &[(2, 0)], mask()),
+        Err(MineError::OutOfBounds((2, 0)))
+    );
+    assert_eq!(
+        GameState::from_mines_masked(2, 2, &[(0, 5)], None),
+        Err(MineError::OutOfBounds((0, 5)))
+    );
+    assert!(matches!(
+        GameState::from_mines_masked(2, 2, &[(1, 0)], mask()),
+        Err(MineError::InvalidMask(_))
+    ));
+}
+
+#[test]
+fn computed_numbers_match_the_stored_ones() {
+    for seed in 0..5 {
+        let state = initial_state_seeded(16, 16, 40, seed);
+        for point in state.grid.points() {
+            if let CellType::Empty { adjacent_mines } = state.grid[point].cell_type {
+                assert_eq!(
+                    compute_cell_adjacency(&state, point),
+                    adjacent_mines,
+                    "seed {} {:?}",
+                    seed,
+                    point
+                );
+            }
+        }
+    }
+
+    // A mine counts the mines around it
+    let state = GameState::from_mines(3, 1, &[(0, 0), (1, 0)]);
+    assert_eq!(compute_cell_adjacency(&state, (0, 0)), 1);
+}
+
+#[test]
+fn fog_hides_the_numbers_far_from_the_last_click() {
+    // . . . . . 1 *
+    let mut state = GameState::from_mines(7, 1, &[(6, 0)]);
+    state.rules.fog_radius = Some(2);
+    let state = open_cell(state, (0, 0));
+    let row = &state.board_view()[0];
+    assert_eq!(row[1], CellView::Number(0));
+    assert_eq!(row[4], CellView::Opened);
+    assert_eq!(row[5], CellView::Opened);
+    assert_eq!(row[6], CellView::Closed(Flag::Unflagged));
+
+    // Only the view changes
+    let mut clear = state.clone();
+    clear.rules.fog_radius = Some(5);
+    assert_eq!(clear.board_view()[0][5], CellView::Number(1));
+    assert_eq!(clear.grid, state.grid);
+}
+
+#[test]
+fn solution_view_shows_the_whole_layout() {
+    let state = initial_state_seeded(9, 9, 10, 4);
+    let mine = state.mine_positions()[0];
+    let state = open_cell(state, mine);
+    assert_eq!(state.status, GameStatus::Lost);
+
+    let layout = GameState::from_mines(9, 9, &state.mine_positions());
+    let view = state.solution_view();
+    for (x, y) in layout.grid.points() {
+        let expected = match layout.grid[(x, y)].cell_type {
+            _ if (x, y) == mine => CellView::ExplodedMine,
+            CellType::Mine => CellView::Mine,
+            CellType::Empty { adjacent_mines } => CellView::Number(adjacent_mines),
+        };
+        assert_eq!(view[y][x], expected, "{:?}", (x, y));
+    }
+}
+
+#[test]
+fn repair_adjacency_rewrites_wrong_numbers() {
+    let state = initial_state_seeded(9, 9, 10, 2);
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    let mut broken = state.clone();
+    for point in state.grid.points() {
+        if let CellType::Empty { .. } = broken.grid[point].cell_type {
+            broken.grid[point].cell_type = CellType::Empty { adjacent_mines: 7 };
+        }
+    }
+    assert_ne!(broken, state);
+    assert_eq!(repair_adjacency(broken), state);
+}
+
+#[test]
+fn density_map_counts_around_mines_too() {
+    // * * 2
+    // 3 * 2
+    // 1 1 1
+    let state = GameState::from_mines(3, 3, &[(0, 0), (1, 0), (1, 1)]);
+    assert_eq!(
+        density_map(&state),
+        vec![vec![2, 2, 2], vec![3, 2, 2], vec![1, 1, 1]]
+    );
+
+    let state = initial_state_seeded(16, 16, 40, 8);
+    let map = density_map(&state);
+    for (x, y) in state.grid.points() {
+        if let CellType::Empty { adjacent_mines } = state.grid[(x, y)].cell_type {
+            assert_eq!(map[y][x], adjacent_mines);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "env")]
+
+use rusty_mines::env::*;
+use rusty_mines::*;
+
+fn find(env: &Env, wanted: impl Fn(CellType) -> bool) -> Point {
+    let grid = &env.state().grid;
+    grid.points()
+        .find(|&point| wanted(grid[point].cell_type))
+        .unwrap()
+}
+
+#[test]
+fn a_mine_ends_the_episode_with_a_penalty() {
+    let mut env = Env::new(9, 9, 10, 3);
+    let number = find(
+        &env,
+        |cell| matches!(cell, CellType::Empty { adjacent_mines } if adjacent_mines > 0),
+    );
+    let (observation, reward, done) = env.step(number);
+    assert!(reward > 0.0);
+    assert!(!done);
+    assert!(observation[number.1][number.0] > 0);
+
+    // Clicking the same cell again is a wasted step
+    let (_, reward, done) = env.step(number);
+    assert!(reward < 0.0);
+    assert!(!done);
+
+    let mine = find(&env, |cell| cell == CellType::Mine);
+    let (_, reward, done) = env.step(mine);
+    assert_eq!(reward, MINE_REWARD);
+    assert!(done);
+    let (_, reward, done) = env.step(number);
+    assert_eq!(reward, 0.0);
+    assert!(done);
+
+    let observation = env.reset();
+    assert!(observation.iter().flatten().all(|&cell| cell == -1));
+    assert_eq!(env.state().status, GameStatus::InProgress);
+}
+
+#[test]
+fn a_win_ends_the_episode_with_a_bonus() {
+    let mut env = Env::new(5, 5, 3, 8);
+    let safe = env
+        .state()
+        .grid
+        .points()
+        .filter(|&point| env.state().grid[point].cell_type != CellType::Mine)
+        .collect::<Vec<Point>>();
+    let mut last = (0.0, false);
+    for point in safe {
+        if env.state().grid[point].state == CellState::Opened {
+            continue;
+        }
+        let (_, reward, done) = env.step(point);
+        last = (reward, done);
+    }
+    assert_eq!(last, (WIN_REWARD, true));
+    assert_eq!(env.state().status, GameStatus::Won);
+}
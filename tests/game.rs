@@ -0,0 +1,341 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rusty_mines::*;
+
+#[test]
+fn a_perfect_clear_is_fully_efficient() {
+    let mut game = Game::new(GameState::from_mines(4, 4, &[(1, 1), (3, 3)]));
+    for point in minimal_winning_clicks(&game.state) {
+        game.open(point);
+    }
+    assert_eq!(game.state.status, GameStatus::Won);
+    assert_eq!(game.clicks(), 5);
+    assert_eq!(game.efficiency(), Some(1.0));
+}
+
+#[test]
+fn wasted_clicks_lower_the_efficiency() {
+    let mut game = Game::new(GameState::from_mines(4, 4, &[(1, 1), (3, 3)]));
+    assert_eq!(game.efficiency(), None);
+    // Row by row, clicking cells the cascades already opened too
+    for point in game.state.grid.points().collect::<Vec<Point>>() {
+        if game.state.grid[point].cell_type != CellType::Mine {
+            game.open(point);
+        }
+    }
+    assert_eq!(game.state.status, GameStatus::Won);
+    assert!(game.clicks() > 5);
+    assert_eq!(game.efficiency(), Some(5.0 / f64::from(game.clicks())));
+}
+
+#[test]
+fn a_chord_is_one_click() {
+    let mut game = Game::new(GameState::from_mines(3, 1, &[(0, 0)]));
+    game.open((1, 0));
+    game.flag((0, 0), Flag::Sure);
+    game.chord((1, 0));
+    assert_eq!(game.state.status, GameStatus::Won);
+    assert_eq!((game.open_clicks(), game.chord_clicks()), (1, 1));
+    assert_eq!(game.efficiency(), Some(0.5));
+}
+
+#[test]
+fn clicks_after_the_game_ends_are_not_counted() {
+    let mut game = Game::new(GameState::from_mines(3, 1, &[(0, 0)]));
+    game.open((0, 0));
+    game.open((2, 0));
+    assert_eq!(game.state.status, GameStatus::Lost);
+    assert_eq!(game.clicks(), 1);
+    assert_eq!(game.efficiency(), None);
+}
+
+#[test]
+fn a_preset_game_carries_its_metadata() {
+    let before = std::time::SystemTime::now();
+    let game = Game::from_difficulty(Difficulty::Expert, Some(12345));
+    let meta = &game.meta;
+    assert_eq!(meta.seed, Some(12345));
+    assert!(matches!(meta.difficulty, Difficulty::Expert));
+    assert_eq!((meta.width, meta.height, meta.mines), (30, 16, 99));
+    assert!(meta.created_at >= before);
+    assert_eq!(meta.difficulty.to_string(), "Expert");
+    assert_eq!(game.state, initial_state_seeded(30, 16, 99, 12345));
+}
+
+#[test]
+fn a_hand_built_game_is_custom_without_a_seed() {
+    let game = Game::new(GameState::from_mines(4, 3, &[(0, 0), (3, 2)]));
+    assert_eq!(game.meta.seed, None);
+    assert!(matches!(
+        game.meta.difficulty,
+        Difficulty::Custom {
+            width: 4,
+            height: 3,
+            mines: 2
+        }
+    ));
+}
+
+#[test]
+fn retry_keep_flags_keeps_only_the_flags() {
+    let mut game = Game::from_difficulty(Difficulty::Beginner, Some(5));
+    let mines = game.state.mine_positions();
+    let safe = game
+        .state
+        .grid
+        .points()
+        .filter(|point| !mines.contains(point))
+        .collect::<Vec<Point>>();
+    game.flag(mines[0], Flag::Sure);
+    game.flag(safe[0], Flag::Unsure);
+    game.open(safe[1]);
+    game.open(mines[1]);
+    assert_eq!(game.state.status, GameStatus::Lost);
+
+    let retry = game.retry_keep_flags();
+    assert_eq!(retry.status, GameStatus::InProgress);
+    assert_eq!(retry.lives, 1);
+    assert_eq!(retry.mine_positions(), mines);
+    for point in retry.grid.points() {
+        let expected = match point {
+            _ if point == mines[0] => CellState::Unopened(Flag::Sure),
+            _ if point == safe[0] => CellState::Unopened(Flag::Unsure),
+            _ => CellState::Unopened(Flag::Unflagged),
+        };
+        assert_eq!(retry.grid[point].state, expected, "{:?}", point);
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn states_and_games_are_send_and_sync() {
+    assert_send_sync::<GameState>();
+    assert_send_sync::<Game>();
+}
+
+#[test]
+fn a_state_can_be_played_on_another_thread() {
+    let state = initial_state_seeded(9, 9, 10, 1);
+    let mines = state.mine_positions();
+    let state = std::thread::spawn(move || surrender(state)).join().unwrap();
+    assert_eq!(state.status, GameStatus::Lost);
+    assert_eq!(state.mine_positions(), mines);
+}
+
+#[test]
+fn an_assisted_game_plays_the_forced_moves_for_the_player() {
+    let mut game = Game::new(GameState::from_mines(5, 3, &[(3, 2)]));
+    game.assist = true;
+    // (4, 2) is the only safe cell the cascade doesn't reach
+    game.open((4, 0));
+    assert_eq!(game.state.status, GameStatus::Won);
+    assert_eq!(game.clicks(), 1);
+}
+
+#[test]
+fn a_won_games_report_adds_up() {
+    let mut game = Game::new(GameState::from_mines(3, 3, &[(0, 0)]));
+    game.flag((0, 0), Flag::Sure);
+    game.flag((2, 0), Flag::Sure);
+    game.flag((2, 0), Flag::Unflagged);
+    game.open((2, 2));
+    game.open((1, 1));
+
+    let report = game_report(&game);
+    assert_eq!(report.status, GameStatus::Won);
+    assert_eq!(report.clicks, report.open_clicks + report.chord_clicks);
+    assert_eq!((report.clicks, report.board_3bv), (1, 1));
+    assert_eq!(report.efficiency, Some(1.0));
+    assert!(report.efficiency.unwrap() <= 1.0);
+    assert_eq!(report.flag_accuracy, Some(0.5));
+    assert!(!report.forced_guess);
+    assert!(report.to_string().starts_with("Won in "));
+}
+
+#[test]
+fn the_solver_clears_a_sandbox_in_one_click() {
+    let mut game = Game::new(GameState::sandbox(8, 8));
+    auto_play(&mut game, (3, 3));
+    let report = game_report(&game);
+    assert_eq!(report.status, GameStatus::Won);
+    assert_eq!(
+        (report.clicks, report.open_clicks, report.chord_clicks),
+        (1, 1, 0)
+    );
+}
+
+#[test]
+fn the_solver_counts_its_chords_apart_from_its_opens() {
+    for seed in 0..5 {
+        let mut game = Game::from_difficulty(Difficulty::Beginner, Some(seed));
+        auto_play(&mut game, (4, 4));
+        let report = game_report(&game);
+        assert_eq!(report.clicks, report.open_clicks + report.chord_clicks);
+        assert!(report.open_clicks >= 1);
+    }
+}
+
+#[test]
+fn a_saved_game_relocates_the_first_mine_like_an_unsaved_one() {
+    let mut game = Game::from_difficulty(Difficulty::Intermediate, Some(17));
+    game.safe_first_click = true;
+    game.flag((0, 0), Flag::Unsure);
+    let mine = game.state.mine_positions()[3];
+
+    let mut resumed = load_game(&save_game(&game)).unwrap();
+    assert!(resumed.safe_first_click);
+    game.open(mine);
+    resumed.open(mine);
+    assert_ne!(game.state.grid[mine].cell_type, CellType::Mine);
+    assert_eq!(resumed.state, game.state);
+    assert_eq!(game.state.status, GameStatus::InProgress);
+
+    // The RNG picks up after the words the relocation drew
+    let text = save_game(&game);
+    assert_eq!(save_game(&load_game(&text).unwrap()), text);
+}
+
+// A plus sign with mines on three of its arms, leaving the middle and the
+// bottom arm as the only safe cells
+fn walled_game() -> Game {
+    let mask = vec![
+        vec![false, true, false],
+        vec![true, true, true],
+        vec![false, true, false],
+    ];
+    Game::new(GameState::from_mines_masked(3, 3, &[(1, 0), (0, 1), (2, 1)], Some(mask)).unwrap())
+}
+
+#[test]
+fn a_first_click_mine_moves_to_a_playable_cell() {
+    let mut game = walled_game();
+    game.safe_first_click = true;
+    game.open((1, 0));
+    assert_eq!(game.state.status, GameStatus::InProgress);
+    assert_eq!(game.state.grid[(1, 0)].state, CellState::Opened);
+
+    let mines = game.state.mine_positions();
+    assert_eq!(mines.len(), 3);
+    assert!(mines.iter().all(|&point| !game.state.grid.is_wall(point)));
+    assert_eq!(game.state.grid.mask(), walled_game().state.grid.mask());
+}
+
+#[test]
+fn retries_keep_the_walls() {
+    let mut game = walled_game();
+    game.flag((0, 1), Flag::Sure);
+    game.open((1, 2));
+    let mask = game.state.grid.mask().cloned();
+    assert!(mask.is_some());
+
+    let retry = game.retry_keep_flags();
+    assert_eq!(retry.grid.mask(), mask.as_ref());
+    assert_eq!(retry.grid[(0, 0)].state, CellState::Opened);
+    assert_eq!(retry.grid[(0, 1)].state, CellState::Unopened(Flag::Sure));
+
+    let retry = game.retry_with_first_move();
+    assert_eq!(retry.state.grid.mask(), mask.as_ref());
+    assert_eq!(retry.state.grid[(1, 2)].state, CellState::Opened);
+}
+
+#[test]
+fn a_guess_seed_replays_the_same_simulation() {
+    let simulate = |guess_seed: u64| {
+        let mut rng = StdRng::seed_from_u64(guess_seed);
+        (0..20)
+            .map(|seed| {
+                let mut game = Game::from_difficulty(Difficulty::Beginner, Some(seed));
+                auto_play_with_rng(&mut game, (4, 4), &mut rng);
+                (game.state.status, game.clicks())
+            })
+            .collect::<Vec<(GameStatus, u32)>>()
+    };
+    let first = simulate(7);
+    assert_eq!(simulate(7), first);
+    assert!(first
+        .iter()
+        .all(|&(status, _)| status != GameStatus::InProgress));
+}
+
+#[test]
+fn a_seeded_hint_picks_the_same_guess() {
+    // * 1 .
+    // 1 1 .
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = open_cell(state, (2, 0));
+    let hint = |seed| next_hint_with_rng(&state, &mut StdRng::seed_from_u64(seed));
+    for seed in 0..10 {
+        let Some(Hint::BestGuess(point, chance)) = hint(seed) else {
+            panic!("no guess for seed {}", seed);
+        };
+        assert!(point == (0, 0) || point == (0, 1));
+        assert_eq!(chance, 0.5);
+        assert_eq!(hint(seed), Some(Hint::BestGuess(point, chance)));
+    }
+}
+
+#[test]
+fn a_retry_starts_with_the_first_click_played() {
+    let mut game = Game::from_difficulty(Difficulty::Beginner, Some(9));
+    let zero = game
+        .state
+        .grid
+        .points()
+        .find(|&point| game.state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        .unwrap();
+    game.open(zero);
+    let opened = game.state.clone();
+    game.open(game.state.mine_positions()[0]);
+    assert_eq!(game.state.status, GameStatus::Lost);
+
+    let retry = game.retry_with_first_move();
+    assert_eq!(retry.state, opened);
+    assert_eq!(retry.state.status, GameStatus::InProgress);
+    assert_eq!(retry.open_clicks(), 1);
+}
+
+#[test]
+fn on_move_hears_each_move_once() {
+    let heard = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = std::sync::Arc::clone(&heard);
+    let mut game = Game::new(GameState::from_mines(4, 4, &[(1, 1), (3, 3)]));
+    game.set_on_move(Some(Box::new(move |action, state| {
+        log.lock().unwrap().push((*action, state.opens));
+    })));
+
+    game.open((3, 0));
+    game.flag((1, 1), Flag::Sure);
+    game.chord((2, 0));
+    // Clones start without the callback
+    game.clone().open((0, 3));
+    assert_eq!(
+        *heard.lock().unwrap(),
+        vec![
+            (Move::Open((3, 0)), 1),
+            (Move::Flag((1, 1), Flag::Sure), 1),
+            (Move::Chord((2, 0)), 2)
+        ]
+    );
+}
+
+#[test]
+fn parallel_simulation_matches_the_serial_one() {
+    for strategy in [Strategy::Solver, Strategy::Random] {
+        let serial = simulate(Difficulty::Beginner, 24, 100, strategy);
+        assert_eq!(serial.games, 24);
+        for threads in [1, 3, 8] {
+            assert_eq!(
+                simulate_parallel(Difficulty::Beginner, 24, 100, threads, strategy),
+                serial,
+                "{:?} on {} threads",
+                strategy,
+                threads
+            );
+        }
+    }
+    assert_eq!(
+        simulate_parallel(Difficulty::Beginner, 0, 100, 4, Strategy::Solver),
+        SimulationResult::default()
+    );
+}
@@ -0,0 +1,460 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rusty_mines::*;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[test]
+fn dense_layouts_are_quick_and_exact() {
+    let started = Instant::now();
+    for seed in 0..20 {
+        // 90% of the cells are mines
+        let state = initial_state_seeded(30, 30, 810, seed);
+        assert_eq!(state.mine_positions().len(), 810);
+
+        let layout = generate_layout_shuffle(30, 30, 810, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(layout.iter().collect::<HashSet<&Point>>().len(), 810);
+    }
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn every_cell_but_one_can_be_a_mine() {
+    let state = initial_state_seeded(5, 5, 24, 1);
+    assert_eq!(state.mine_positions().len(), 24);
+    assert_eq!(state.safe_cells_remaining(), 1);
+}
+
+fn no_guess_params(
+    width: usize,
+    height: usize,
+    mines: usize,
+    max_attempts: usize,
+) -> GenerationParams {
+    GenerationParams {
+        width,
+        height,
+        mines,
+        first_click: (0, 0),
+        seed: Some(3),
+        max_attempts,
+        distribution: MineDistribution::Uniform,
+        safe_zone: None,
+        safe_zone_neighbors: false,
+    }
+}
+
+#[test]
+fn cancelling_partway_stops_generation() {
+    // Far too dense to be solvable, so only the flag can end it early
+    let params = no_guess_params(9, 9, 60, 1000);
+    let cancel = AtomicBool::new(false);
+    let mut tried = 0;
+    let result = generate_no_guess_with_progress(
+        &params,
+        |attempt| {
+            tried = attempt;
+            if attempt == 3 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        },
+        &cancel,
+    );
+    assert_eq!(result, Err(MineError::Cancelled));
+    assert_eq!(tried, 3);
+}
+
+#[test]
+fn generation_gives_up_after_max_attempts() {
+    let params = no_guess_params(9, 9, 60, 5);
+    let mut calls = Vec::new();
+    let result = generate_no_guess_with_progress(
+        &params,
+        |attempt| calls.push(attempt),
+        &AtomicBool::new(false),
+    );
+    assert_eq!(result, Err(MineError::NoSolvableLayout { attempts: 5 }));
+    assert_eq!(calls, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn generated_boards_need_no_guess() {
+    let params = no_guess_params(9, 9, 10, 1000);
+    let state = generate_no_guess(&params).unwrap();
+    assert_eq!(state.mine_positions().len(), 10);
+    assert!(is_solvable_without_guessing(&state, (0, 0)));
+}
+
+#[test]
+fn symmetric_layouts_mirror_onto_themselves() {
+    for axis in [
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Rotational,
+    ] {
+        for (width, height, mines) in [(8, 6, 12), (9, 7, 13), (5, 5, 5), (1, 9, 3)] {
+            let state = initial_state_symmetric(width, height, mines, axis).unwrap();
+            let layout = state.mine_positions();
+            assert_eq!(layout.len(), mines, "{:?} {}x{}", axis, width, height);
+            let mirrored = layout
+                .iter()
+                .map(|&point| axis.mirror(point, width, height))
+                .collect::<HashSet<Point>>();
+            assert_eq!(
+                mirrored,
+                layout.into_iter().collect::<HashSet<Point>>(),
+                "{:?} {}x{}",
+                axis,
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn an_odd_mine_needs_a_cell_on_the_axis() {
+    // Even dimensions leave no cell that is its own mirror
+    assert_eq!(
+        initial_state_symmetric(4, 4, 3, Symmetry::Rotational).map(|state| state.grid.width()),
+        Err(MineError::NoSymmetricLayout { mines: 3 })
+    );
+    let state = initial_state_symmetric(5, 5, 3, Symmetry::Rotational).unwrap();
+    assert_eq!(state.grid[(2, 2)].cell_type, CellType::Mine);
+}
+
+#[test]
+fn shuffled_layouts_are_exact_unique_and_in_bounds() {
+    for (width, height, mines) in [(9, 9, 10), (16, 16, 40), (7, 3, 21), (1, 1, 0)] {
+        let layout = generate_layout_shuffle(width, height, mines, &mut StdRng::seed_from_u64(9));
+        assert_eq!(layout.len(), mines);
+        assert_eq!(layout.iter().collect::<HashSet<&Point>>().len(), mines);
+        assert!(layout.iter().all(|&(x, y)| x < width && y < height));
+        // The layout only depends on the RNG
+        assert_eq!(
+            layout,
+            generate_layout_shuffle(width, height, mines, &mut StdRng::seed_from_u64(9))
+        );
+    }
+}
+
+// Mines on the border ring and on the 6x6 middle of a 10x10 board, over many
+// layouts. Both areas have 36 cells.
+fn edge_and_center_mines(distribution: MineDistribution) -> (usize, usize) {
+    let mut rng = StdRng::seed_from_u64(21);
+    let (mut edge, mut center) = (0, 0);
+    for _ in 0..200 {
+        let layout = generate_layout_weighted(10, 10, 20, distribution, &mut rng);
+        assert_eq!(layout.iter().collect::<HashSet<&Point>>().len(), 20);
+        for &(x, y) in &layout {
+            let depth = x.min(9 - x).min(y.min(9 - y));
+            match depth {
+                0 => edge += 1,
+                2.. => center += 1,
+                _ => (),
+            }
+        }
+    }
+    (edge, center)
+}
+
+#[test]
+fn edge_heavy_layouts_crowd_the_border() {
+    let (edge, center) = edge_and_center_mines(MineDistribution::EdgeHeavy);
+    assert!(
+        edge * 2 > center * 3,
+        "{} on the edge, {} in the middle",
+        edge,
+        center
+    );
+}
+
+#[test]
+fn center_heavy_layouts_crowd_the_middle() {
+    let (edge, center) = edge_and_center_mines(MineDistribution::CenterHeavy);
+    assert!(
+        center > edge * 2,
+        "{} on the edge, {} in the middle",
+        edge,
+        center
+    );
+}
+
+#[test]
+fn a_pre_opened_zero_cascades() {
+    // . . . .
+    // . . 1 1
+    // . . 1 *
+    let state = initial_state_with_openings(4, 3, &[(3, 2)], &[(0, 0)]).unwrap();
+    for point in state.grid.points() {
+        let opened = state.grid[point].state == CellState::Opened;
+        assert_eq!(opened, ![(3, 2)].contains(&point), "{:?}", point);
+    }
+}
+
+#[test]
+fn pre_opening_rejects_mines_and_repeated_mines() {
+    assert_eq!(
+        initial_state_with_openings(4, 3, &[(3, 2)], &[(3, 2)]),
+        Err(MineError::PreOpenedMine((3, 2)))
+    );
+    assert_eq!(
+        initial_state_with_openings(4, 3, &[(3, 2), (3, 2)], &[]),
+        Err(MineError::DuplicateMine((3, 2)))
+    );
+    assert_eq!(
+        initial_state_with_openings(4, 3, &[(3, 2)], &[(4, 0)]),
+        Err(MineError::OutOfBounds((4, 0)))
+    );
+}
+
+#[test]
+fn generate_many_follows_the_derived_seeds() {
+    let boards = generate_many(Difficulty::Beginner, 5, 100);
+    assert_eq!(boards.len(), 5);
+    for (board, seed) in boards.iter().zip(100..) {
+        assert_eq!(board.mine_positions().len(), 10);
+        assert_eq!(*board, initial_state_seeded(9, 9, 10, seed));
+    }
+    for pair in boards.windows(2) {
+        assert_ne!(pair[0].mine_positions(), pair[1].mine_positions());
+    }
+}
+
+fn date(year: i32, month: u32, day: u32) -> CalendarDate {
+    CalendarDate { year, month, day }
+}
+
+#[test]
+fn everyone_gets_the_same_board_of_the_day() {
+    let today = date(2024, 3, 9);
+    assert_eq!(today.seed(), 20240309);
+    assert_eq!(
+        daily_board(today, Difficulty::Expert),
+        daily_board(today, Difficulty::Expert)
+    );
+
+    let mut layouts = HashSet::new();
+    for day in 1..=28 {
+        layouts.insert(daily_board(date(2024, 2, day), Difficulty::Beginner).mine_positions());
+    }
+    assert_eq!(layouts.len(), 28);
+}
+
+#[test]
+fn days_since_the_epoch_become_calendar_dates() {
+    assert_eq!(CalendarDate::from_days_since_epoch(0), date(1970, 1, 1));
+    assert_eq!(
+        CalendarDate::from_days_since_epoch(19_791),
+        date(2024, 3, 9)
+    );
+    assert_eq!(
+        CalendarDate::from_days_since_epoch(11_016),
+        date(2000, 2, 29)
+    );
+    assert_eq!(CalendarDate::from_days_since_epoch(-1), date(1969, 12, 31));
+}
+
+#[test]
+fn a_phrase_always_gives_the_same_board() {
+    let board = initial_state_from_phrase("blue-whale-42", Difficulty::Intermediate);
+    assert_eq!(
+        board,
+        initial_state_from_phrase("blue-whale-42", Difficulty::Intermediate)
+    );
+    assert_eq!(board.mine_positions().len(), 40);
+    assert_ne!(
+        board.mine_positions(),
+        initial_state_from_phrase("blue-whale-43", Difficulty::Intermediate).mine_positions()
+    );
+    // The empty phrase hashes to the FNV-1a offset basis
+    assert_eq!(
+        initial_state_from_phrase("", Difficulty::Beginner),
+        initial_state_seeded(9, 9, 10, 0xcbf2_9ce4_8422_2325)
+    );
+}
+
+// Fills the top row from the left, ignoring the rng
+struct TopRow;
+
+impl MinePlacer for TopRow {
+    fn place(
+        &self,
+        width: usize,
+        _height: usize,
+        mines: usize,
+        _first_click: Option<Point>,
+        _rng: &mut dyn rand::RngCore,
+    ) -> Result<Vec<Point>, MineError> {
+        Ok((0..mines.min(width)).map(|x| (x, 0)).collect())
+    }
+}
+
+#[test]
+fn a_custom_placer_lays_out_the_board() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let state = initial_state_with_placer(5, 3, 5, None, &TopRow, &mut rng).unwrap();
+    assert_eq!(
+        state.mine_positions(),
+        vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]
+    );
+    assert_eq!(
+        state.grid[(2, 1)].cell_type,
+        CellType::Empty { adjacent_mines: 3 }
+    );
+
+    // A placer that comes up short is caught
+    assert_eq!(
+        initial_state_with_placer(5, 3, 7, None, &TopRow, &mut rng),
+        Err(MineError::InvalidPlacement {
+            expected: 7,
+            placed: 5
+        })
+    );
+}
+
+#[test]
+fn the_built_in_placers_keep_the_first_click_clear() {
+    let mut rng = StdRng::seed_from_u64(4);
+    for seed in 0..20 {
+        let placed = MineDistribution::EdgeHeavy
+            .place(6, 6, 30, Some((0, 0)), &mut StdRng::seed_from_u64(seed))
+            .unwrap();
+        assert_eq!(placed.len(), 30);
+        assert!(!placed.contains(&(0, 0)));
+    }
+    let state =
+        initial_state_with_placer(6, 6, 30, Some((5, 5)), &MineDistribution::Uniform, &mut rng)
+            .unwrap();
+    assert_ne!(state.grid[(5, 5)].cell_type, CellType::Mine);
+}
+
+#[test]
+fn the_built_in_placers_refuse_an_empty_board() {
+    let placers: [&dyn MinePlacer; 2] = [
+        &MineDistribution::Uniform,
+        &NoGuess {
+            distribution: MineDistribution::Uniform,
+            max_attempts: 10,
+        },
+    ];
+    for placer in placers {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            placer.place(0, 4, 1, None, &mut rng),
+            Err(MineError::EmptyBoard)
+        );
+        assert_eq!(
+            placer.place(4, 0, 0, Some((0, 0)), &mut rng),
+            Err(MineError::EmptyBoard)
+        );
+    }
+}
+
+#[test]
+fn zero_weight_cells_never_get_a_mine() {
+    // Only the left half of the board can take mines
+    let weights = (0..6)
+        .map(|_| {
+            (0..8)
+                .map(|x| if x < 4 { 1.0 + x as f64 } else { 0.0 })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..50 {
+        let state = initial_state_weighted(8, 6, 20, &weights, &mut rng).unwrap();
+        let mines = state.mine_positions();
+        assert_eq!(mines.len(), 20);
+        assert!(mines.iter().all(|&(x, _)| x < 4), "{:?}", mines);
+    }
+}
+
+#[test]
+fn bad_weights_are_rejected() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let short = vec![vec![1.0; 3]; 2];
+    let mut negative = vec![vec![1.0; 3]; 3];
+    negative[1][2] = -0.5;
+    let mut nan = vec![vec![1.0; 3]; 3];
+    nan[0][0] = f64::NAN;
+    let mut sparse = vec![vec![0.0; 3]; 3];
+    sparse[2][2] = 1.0;
+
+    for weights in [short, negative, nan, sparse] {
+        assert!(matches!(
+            initial_state_weighted(3, 3, 2, &weights, &mut rng),
+            Err(MineError::InvalidWeights(_))
+        ));
+    }
+}
+
+#[test]
+fn campaign_levels_get_denser_and_stay_guess_free() {
+    let levels = campaign(5, 12).unwrap();
+    assert_eq!(levels.len(), 5);
+    let density =
+        |state: &GameState| state.mine_positions().len() as f64 / state.total_cells() as f64;
+    for pair in levels.windows(2) {
+        assert!(density(&pair[1]) > density(&pair[0]));
+        assert!(pair[1].total_cells() > pair[0].total_cells());
+    }
+    for state in &levels {
+        let center = ((state.grid.width() - 1) / 2, (state.grid.height() - 1) / 2);
+        assert!(is_solvable_without_guessing(state, center));
+    }
+    assert_eq!(campaign(5, 12), Ok(levels));
+}
+
+#[test]
+fn seeded_shuffle_depends_only_on_the_seed() {
+    let shuffled = |seed| {
+        let mut items = (0..20).collect::<Vec<u32>>();
+        seeded_shuffle(&mut items, seed);
+        items
+    };
+    assert_eq!(shuffled(5), shuffled(5));
+    assert_ne!(shuffled(5), shuffled(6));
+    let mut sorted = shuffled(5);
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..20).collect::<Vec<u32>>());
+}
+
+#[test]
+fn no_mine_lands_in_the_safe_zone() {
+    let top_row = (0..9).map(|x| (x, 0)).collect::<Vec<Point>>();
+    for seed in 0..10 {
+        let mut params = no_guess_params(9, 9, 10, 1000);
+        params.seed = Some(seed);
+        params.first_click = (4, 4);
+        params.safe_zone = Some(top_row.clone());
+        let state = generate_no_guess(&params).unwrap();
+        assert!(state.mine_positions().iter().all(|&(_, y)| y > 0));
+
+        // Their neighbors make it the top two rows
+        params.safe_zone_neighbors = true;
+        let state = generate_no_guess(&params).unwrap();
+        assert!(state.mine_positions().iter().all(|&(_, y)| y > 1));
+    }
+}
+
+#[test]
+fn a_safe_zone_must_fit_the_board_and_leave_room() {
+    let mut params = no_guess_params(4, 4, 8, 10);
+    params.safe_zone = Some(vec![(0, 0), (4, 0)]);
+    assert_eq!(
+        generate_no_guess(&params),
+        Err(MineError::OutOfBounds((4, 0)))
+    );
+
+    // The top two rows and the first click in the corner with its neighbors
+    // leave 4 cells for 8 mines
+    params.safe_zone = Some((0..4).map(|x| (x, 0)).collect());
+    params.safe_zone_neighbors = true;
+    params.first_click = (0, 3);
+    assert_eq!(
+        generate_no_guess(&params),
+        Err(MineError::TooManyMines { mines: 8, cells: 4 })
+    );
+}
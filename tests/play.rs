@@ -0,0 +1,839 @@
+use rusty_mines::*;
+
+fn sorted(mut points: Vec<Point>) -> Vec<Point> {
+    points.sort_unstable_by_key(|&(x, y)| (y, x));
+    points
+}
+
+#[test]
+fn highlight_safe_gives_the_closed_neighbors_of_a_satisfied_number() {
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = open_cell(state, (1, 1));
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let before = state.clone();
+
+    assert_eq!(
+        sorted(highlight_safe(&state, (1, 1))),
+        vec![(1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)]
+    );
+    assert_eq!(state, before);
+}
+
+#[test]
+fn highlight_safe_gives_nothing_for_an_unsatisfied_number() {
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = open_cell(state, (1, 1));
+
+    assert_eq!(highlight_safe(&state, (1, 1)), Vec::new());
+    // Closed cells and mines can't be chorded either
+    assert_eq!(highlight_safe(&state, (2, 2)), Vec::new());
+    assert_eq!(highlight_safe(&state, (0, 0)), Vec::new());
+}
+
+#[test]
+fn hitting_a_mine_costs_a_life() {
+    let state = GameState {
+        lives: 2,
+        ..GameState::from_mines(3, 3, &[(0, 0), (2, 2)])
+    };
+
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.lives, 1);
+    assert_eq!(state.status, GameStatus::InProgress);
+    assert_eq!(state.grid[(0, 0)].state, CellState::Opened);
+    assert_eq!(state.lost_at, None);
+
+    // The opened mine can't be hit again
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.lives, 1);
+
+    let state = open_cell(state, (2, 2));
+    assert_eq!(state.lives, 0);
+    assert_eq!(state.status, GameStatus::Lost);
+    assert_eq!(state.lost_at, Some((2, 2)));
+}
+
+#[test]
+fn a_game_with_lives_left_can_still_be_won() {
+    let state = GameState {
+        lives: 3,
+        ..GameState::from_mines(3, 1, &[(0, 0)])
+    };
+    let state = open_cell(state, (0, 0));
+    let state = open_cell(state, (1, 0));
+    let state = open_cell(state, (2, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(state.lives, 2);
+}
+
+#[test]
+fn a_board_too_large_to_count_is_refused() {
+    assert_eq!(
+        validate_params(usize::MAX, 2, 1),
+        Err(MineError::BoardTooLarge {
+            width: usize::MAX,
+            height: 2
+        })
+    );
+    let script = format!("new {} 2 1\n", usize::MAX);
+    assert!(matches!(
+        run_script(&script),
+        Err(MineError::InvalidScript { line: 1, .. })
+    ));
+}
+
+#[test]
+fn surrender_shows_the_solution_and_loses() {
+    let state = initial_state_seeded(9, 9, 10, 4);
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    let state = surrender(state);
+
+    assert_eq!(state.status, GameStatus::Lost);
+    for cell in state.grid.iter() {
+        match cell.cell_type {
+            CellType::Empty { .. } => assert_eq!(cell.state, CellState::Opened),
+            CellType::Mine => assert_eq!(cell.state, CellState::Unopened(Flag::Sure)),
+        }
+    }
+}
+
+fn opened_under(state: &GameState, click: Point, cascade_rule: CascadeRule) -> Vec<Point> {
+    let mut state = state.clone();
+    state.rules.cascade_rule = cascade_rule;
+    let state = open_cell(state, click);
+    state
+        .grid
+        .points()
+        .filter(|&point| state.grid[point].state == CellState::Opened)
+        .collect()
+}
+
+#[test]
+fn open_one_more_opens_the_ring_past_the_numbers() {
+    // . 1 * 1
+    // . 1 1 1
+    let state = GameState::from_mines(4, 2, &[(2, 0)]);
+    assert_eq!(
+        opened_under(&state, (0, 0), CascadeRule::StopAtNumbers),
+        vec![(0, 0), (1, 0), (0, 1), (1, 1)]
+    );
+    assert_eq!(
+        opened_under(&state, (0, 0), CascadeRule::OpenOneMore),
+        vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 1)]
+    );
+}
+
+#[test]
+fn open_one_more_goes_one_ring_and_no_further() {
+    for seed in 0..30 {
+        let state = initial_state_seeded(12, 12, 20, seed);
+        let Some(click) = state
+            .grid
+            .points()
+            .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        else {
+            continue;
+        };
+        let stop = opened_under(&state, click, CascadeRule::StopAtNumbers);
+        let more = opened_under(&state, click, CascadeRule::OpenOneMore);
+        assert!(
+            stop.iter().all(|point| more.contains(point)),
+            "seed {}",
+            seed
+        );
+        for point in more.iter().filter(|point| !stop.contains(point)) {
+            // Only neighbors of the numbers the plain cascade stopped at
+            assert!(
+                state
+                    .grid
+                    .adjacent(*point)
+                    .iter()
+                    .any(|adj| stop.contains(adj)
+                        && state.grid[*adj].cell_type != CellType::Empty { adjacent_mines: 0 }),
+                "seed {} {:?}",
+                seed,
+                point
+            );
+            assert_ne!(state.grid[*point].cell_type, CellType::Mine);
+        }
+    }
+}
+
+#[cfg(feature = "assist")]
+#[test]
+fn would_lose_tells_mines_from_safe_cells() {
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    assert!(would_lose(&state, (0, 0)));
+    assert!(!would_lose(&state, (2, 2)));
+
+    // A protected flag can't be opened, and a finished game can't be lost
+    let flagged = change_flag(state.clone(), (0, 0), Flag::Sure);
+    assert!(!would_lose(&flagged, (0, 0)));
+    let won = open_cell(state, (2, 2));
+    assert!(!would_lose(&won, (0, 0)));
+}
+
+#[test]
+fn open_iter_opens_what_open_cell_does() {
+    for seed in 0..20 {
+        let state = initial_state_seeded(9, 9, 10, seed);
+        let Some(click) = state
+            .grid
+            .points()
+            .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        else {
+            continue;
+        };
+        let batch = open_cell(state.clone(), click);
+
+        let mut stepped = state.clone();
+        let yielded = open_iter(&mut stepped, click).collect::<Vec<Point>>();
+        assert_eq!(yielded[0], click);
+        assert_eq!(
+            sorted(yielded),
+            batch
+                .grid
+                .points()
+                .filter(|&point| batch.grid[point].state == CellState::Opened)
+                .collect::<Vec<Point>>(),
+            "seed {}",
+            seed
+        );
+        assert_eq!(stepped, batch, "seed {}", seed);
+    }
+}
+
+#[test]
+fn open_iter_settles_the_status_once_exhausted() {
+    let mut state = GameState::from_mines(3, 1, &[(0, 0)]);
+    let mut cells = open_iter(&mut state, (2, 0));
+    assert_eq!(cells.next(), Some((2, 0)));
+    assert_eq!(cells.next(), Some((1, 0)));
+    assert_eq!(cells.next(), None);
+    drop(cells);
+    assert_eq!(state.status, GameStatus::Won);
+
+    let mut state = GameState::from_mines(3, 1, &[(0, 0)]);
+    assert_eq!(
+        open_iter(&mut state, (0, 0)).collect::<Vec<Point>>(),
+        vec![(0, 0)]
+    );
+    assert_eq!(state.status, GameStatus::Lost);
+}
+
+#[test]
+fn flagging_the_leftover_cells_confirms_the_solve() {
+    let mut state = GameState::from_mines(3, 3, &[(0, 0), (2, 2)]);
+    state.rules.win_condition = WinCondition::FlagAllMines;
+    for point in [(1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)] {
+        state = open_cell(state, point);
+    }
+    assert_eq!(state.status, GameStatus::InProgress);
+    assert_eq!(state.remaining_mines(), 2);
+
+    let state = flag_all_unopened(state, Flag::Sure);
+    assert_eq!(state.remaining_mines(), 0);
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn a_chord_opens_unsure_cells_next_to_a_sure_flag() {
+    let state = GameState::from_mines(3, 1, &[(0, 0)]);
+    let state = open_cell(state, (1, 0));
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = change_flag(state, (2, 0), Flag::Unsure);
+
+    let state = chord(state, (1, 0));
+    assert_eq!(state.grid[(2, 0)].state, CellState::Opened);
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn an_unsure_flag_does_not_satisfy_a_chord() {
+    let state = GameState::from_mines(3, 1, &[(0, 0)]);
+    let state = open_cell(state, (1, 0));
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    let before = state.clone();
+
+    let state = chord(state, (1, 0));
+    assert_eq!(state.grid, before.grid);
+    assert_eq!(state.status, GameStatus::InProgress);
+}
+
+fn cascade_over_unsure(policy: CascadeFlagPolicy, flag: Flag) -> GameState {
+    // . . 1 *
+    let mut state = GameState::from_mines(4, 1, &[(3, 0)]);
+    state.rules.cascade_flag_policy = policy;
+    let state = change_flag(state, (1, 0), flag);
+    open_cell(state, (0, 0))
+}
+
+#[test]
+fn cascades_stop_at_every_flag_by_default() {
+    let state = cascade_over_unsure(CascadeFlagPolicy::default(), Flag::Unsure);
+    assert_eq!(state.grid[(1, 0)].state, CellState::Unopened(Flag::Unsure));
+    assert_eq!(
+        state.grid[(2, 0)].state,
+        CellState::Unopened(Flag::Unflagged)
+    );
+    assert_eq!(state.status, GameStatus::InProgress);
+}
+
+#[test]
+fn cascades_can_clear_unsure_cells() {
+    let state = cascade_over_unsure(CascadeFlagPolicy::RespectSureOnly, Flag::Unsure);
+    assert_eq!(state.grid[(1, 0)].state, CellState::Opened);
+    assert_eq!(state.grid[(2, 0)].state, CellState::Opened);
+    assert_eq!(state.status, GameStatus::Won);
+
+    // Sure flags still hold the cascade back
+    let state = cascade_over_unsure(CascadeFlagPolicy::RespectSureOnly, Flag::Sure);
+    assert_eq!(state.grid[(1, 0)].state, CellState::Unopened(Flag::Sure));
+    assert_eq!(state.status, GameStatus::InProgress);
+}
+
+#[test]
+fn opening_the_last_safe_cell_finalizes_the_win() {
+    // * 1 .
+    // 1 1 .
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = open_cell(state, (2, 0));
+    let state = open_cell(state, (1, 0));
+    assert_eq!(state.status, GameStatus::InProgress);
+    let won = open_cell(state.clone(), (0, 1));
+    assert_eq!(won.status, GameStatus::Won);
+    // Flags are left as they were by default
+    assert_eq!(won.grid[(0, 0)].state, CellState::Unopened(Flag::Unflagged));
+
+    let mut flagging = state;
+    flagging.rules.flag_mines_on_win = WinFlags::FlagMines;
+    let won = open_cell(flagging, (0, 1));
+    assert_eq!(won.status, GameStatus::Won);
+    assert_eq!(won.grid[(0, 0)].state, CellState::Unopened(Flag::Sure));
+    assert_eq!(check_and_finalize(won.clone()), won);
+}
+
+#[test]
+fn check_and_finalize_leaves_an_unfinished_game_alone() {
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    assert_eq!(check_and_finalize(state.clone()), state);
+}
+
+#[test]
+fn practice_mode_flags_a_hit_mine_and_counts_a_mistake() {
+    let mut state = GameState::from_mines(3, 1, &[(0, 0)]);
+    state.rules.practice_mode = PracticeMode::On;
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.status, GameStatus::InProgress);
+    assert_eq!(state.mistakes, 1);
+    assert_eq!(state.grid[(0, 0)].state, CellState::Unopened(Flag::Sure));
+    assert_eq!(state.lives, 1);
+
+    // The mistake doesn't stand in the way of a win
+    let state = open_cell(state, (2, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(state.mistakes, 1);
+}
+
+#[test]
+fn a_practice_flag_can_finish_a_flag_all_mines_game() {
+    let mut state = GameState::from_mines(2, 1, &[(1, 0)]);
+    state.rules.practice_mode = PracticeMode::On;
+    state.rules.win_condition = WinCondition::FlagAllMines;
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.status, GameStatus::InProgress);
+
+    assert_eq!(open_cell(state.clone(), (1, 0)).status, GameStatus::Won);
+    let mut stepped = state;
+    assert_eq!(open_iter(&mut stepped, (1, 0)).count(), 0);
+    assert_eq!(stepped.status, GameStatus::Won);
+    assert_eq!(stepped.mistakes, 1);
+}
+
+#[test]
+fn a_genuine_win_is_a_clean_solve() {
+    let state = open_cell(GameState::from_mines(3, 3, &[(0, 0)]), (2, 2));
+    assert_eq!(state.status, GameStatus::Won);
+    assert!(is_clean_solve(&state));
+}
+
+#[test]
+fn a_win_with_an_opened_mine_is_not_clean() {
+    let mut state = open_cell(GameState::from_mines(3, 3, &[(0, 0)]), (2, 2));
+    state.grid.open((0, 0));
+    assert!(!is_clean_solve(&state));
+
+    // Nor is a hit taken in lives mode
+    let state = GameState {
+        lives: 2,
+        ..GameState::from_mines(3, 3, &[(0, 0)])
+    };
+    let state = open_cell(open_cell(state, (0, 0)), (2, 2));
+    assert_eq!(state.status, GameStatus::Won);
+    assert!(!is_clean_solve(&state));
+
+    // A game still going is not solved at all
+    assert!(!is_clean_solve(&GameState::from_mines(3, 3, &[(0, 0)])));
+}
+
+fn under(win_condition: WinCondition) -> GameState {
+    let mut state = GameState::from_mines(3, 3, &[(0, 0)]);
+    state.rules.win_condition = win_condition;
+    state
+}
+
+#[test]
+fn opening_every_safe_cell_wins_by_default() {
+    let state = open_cell(under(WinCondition::OpenAllSafe), (2, 2));
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn flag_all_mines_also_needs_every_mine_flagged() {
+    let state = open_cell(under(WinCondition::FlagAllMines), (2, 2));
+    assert_eq!(state.status, GameStatus::InProgress);
+    let state = change_flag(state, (0, 0), Flag::Unsure);
+    assert_eq!(state.status, GameStatus::InProgress);
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    assert_eq!(state.status, GameStatus::Won);
+
+    // Flagging first, the last open wins
+    let state = change_flag(under(WinCondition::FlagAllMines), (0, 0), Flag::Sure);
+    assert_eq!(state.status, GameStatus::InProgress);
+    let state = open_cell(state, (2, 2));
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+fn misflagging(feedback: MisflagFeedback) -> GameState {
+    // * 1 . .
+    // 1 1 . .
+    let mut state = GameState::from_mines(4, 2, &[(0, 0)]);
+    state.rules.feedback_on_misflag = feedback;
+    change_flag(state, (1, 1), Flag::Sure)
+}
+
+#[test]
+fn a_misflag_is_left_alone_by_default() {
+    let state = misflagging(MisflagFeedback::default());
+    assert!(state
+        .grid
+        .iter()
+        .all(|cell| cell.state != CellState::Opened));
+}
+
+#[test]
+fn a_misflag_nudges_open_a_safe_neighbor_in_feedback_mode() {
+    let state = misflagging(MisflagFeedback::On);
+    assert_eq!(state.grid[(1, 1)].state, CellState::Unopened(Flag::Sure));
+    // The neighbor showing the fewest mines is a zero, which cascades
+    assert_eq!(state.grid[(2, 0)].state, CellState::Opened);
+    assert_eq!(
+        state.grid[(0, 0)].state,
+        CellState::Unopened(Flag::Unflagged)
+    );
+    assert_eq!(state.status, GameStatus::InProgress);
+
+    // A flag on a mine is no misflag
+    let mut state = GameState::from_mines(4, 2, &[(0, 0)]);
+    state.rules.feedback_on_misflag = MisflagFeedback::On;
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    assert!(state
+        .grid
+        .iter()
+        .all(|cell| cell.state != CellState::Opened));
+}
+
+#[test]
+fn running_out_of_opens_loses_without_a_mine() {
+    let mut state = GameState::from_mines(5, 5, &[(0, 0), (4, 4)]);
+    state.rules.max_opens = Some(2);
+    assert_eq!(state.opens_left(), Some(2));
+    let state = open_cell(state, (1, 0));
+    assert_eq!(state.opens_left(), Some(1));
+    assert_eq!(state.status, GameStatus::InProgress);
+
+    // A click on an opened cell opens nothing and costs nothing
+    let state = open_cell(state, (1, 0));
+    assert_eq!(state.opens_left(), Some(1));
+    let state = open_cell(state, (0, 1));
+    assert_eq!(state.opens_left(), Some(0));
+    assert_eq!(state.status, GameStatus::Lost);
+    assert_eq!(state.lost_at, None);
+}
+
+#[test]
+fn the_last_open_can_still_win() {
+    let mut state = GameState::from_mines(3, 3, &[(0, 0)]);
+    state.rules.max_opens = Some(1);
+    let state = open_cell(state, (2, 2));
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn a_misflag_nudge_is_not_an_open() {
+    let mut state = GameState::from_mines(4, 2, &[(0, 0)]);
+    state.rules.feedback_on_misflag = MisflagFeedback::On;
+    state.rules.max_opens = Some(1);
+    let state = change_flag(state, (1, 1), Flag::Sure);
+    assert_eq!(state.grid[(2, 0)].state, CellState::Opened);
+    assert_eq!(state.opens_left(), Some(1));
+    assert_eq!(state.status, GameStatus::InProgress);
+}
+
+#[test]
+fn a_loss_remembers_the_mine_that_caused_it() {
+    let state = GameState::from_mines(4, 4, &[(0, 0), (3, 3)]);
+    assert_eq!(state.lost_at, None);
+    let state = open_cell(state, (3, 3));
+    assert_eq!(state.status, GameStatus::Lost);
+    assert_eq!(state.lost_at, Some((3, 3)));
+
+    let view = state.board_view();
+    assert_eq!(view[3][3], CellView::ExplodedMine);
+    assert_eq!(view[0][0], CellView::RevealedMine);
+}
+
+#[test]
+fn merging_combines_both_players_progress() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let board = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let a = open_cell(board.clone(), (3, 0));
+    let a = change_flag(a, (1, 1), Flag::Unsure);
+    let b = open_cell(board.clone(), (0, 3));
+    let b = change_flag(b, (1, 1), Flag::Sure);
+    let b = change_flag(b, (1, 0), Flag::Unsure);
+
+    let merged = merge_states(&a, &b).unwrap();
+    for point in merged.grid.points() {
+        let opened = [&a, &b]
+            .iter()
+            .any(|state| state.grid[point].state == CellState::Opened);
+        assert_eq!(
+            merged.grid[point].state == CellState::Opened,
+            opened,
+            "{:?}",
+            point
+        );
+    }
+    assert_eq!(merged.grid[(1, 1)].state, CellState::Unopened(Flag::Sure));
+    assert_eq!(merged.grid[(1, 0)].state, CellState::Unopened(Flag::Unsure));
+    assert_eq!(merged.status, GameStatus::InProgress);
+    assert_eq!(
+        merge_states(&b, &a).unwrap().grid[(1, 1)],
+        merged.grid[(1, 1)]
+    );
+}
+
+#[test]
+fn merging_keeps_cells_opened_without_a_tick() {
+    let board = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let a = open_cell(board.clone(), (3, 0));
+    let mut b = board.clone();
+    b.grid[(2, 2)].state = CellState::Opened;
+    assert_eq!(b.grid.open_order((2, 2)), None);
+
+    let merged = merge_states(&a, &b).unwrap();
+    assert_eq!(merged.grid[(2, 2)].state, CellState::Opened);
+    assert_eq!(merged.grid[(3, 0)].state, CellState::Opened);
+    assert_eq!(merge_states(&b, &a).unwrap().grid, merged.grid);
+}
+
+#[test]
+fn merging_different_layouts_fails() {
+    let a = GameState::from_mines(4, 4, &[(1, 1)]);
+    let b = GameState::from_mines(4, 4, &[(2, 1)]);
+    assert_eq!(merge_states(&a, &b), Err(MineError::LayoutMismatch));
+    let c = GameState::from_mines(5, 4, &[(1, 1)]);
+    assert_eq!(merge_states(&a, &c), Err(MineError::LayoutMismatch));
+}
+
+#[test]
+fn a_sure_flag_protects_its_cell_by_default() {
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let after = open_cell(state.clone(), (0, 0));
+    assert_eq!(after, state);
+    assert_eq!(after.opens, 0);
+}
+
+#[test]
+fn open_all_lets_a_sure_flag_be_opened() {
+    let mut state = GameState::from_mines(3, 3, &[(0, 0)]);
+    state.rules.open_flag_policy = OpenFlagPolicy::OpenAll;
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.status, GameStatus::Lost);
+}
+
+#[test]
+fn an_unsure_flag_can_always_be_opened() {
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = change_flag(state, (2, 2), Flag::Unsure);
+    let state = open_cell(state, (2, 2));
+    assert_eq!(state.grid[(2, 2)].state, CellState::Opened);
+}
+
+#[test]
+fn satisfied_numbers_are_the_ones_their_sure_flags_cover() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    assert_eq!(satisfied_numbers(&state), Vec::new());
+
+    let flagged = change_flag(state.clone(), (1, 1), Flag::Sure);
+    assert_eq!(satisfied_numbers(&flagged), vec![(2, 0), (2, 1)]);
+    let flagged = change_flag(flagged, (3, 3), Flag::Sure);
+    assert_eq!(
+        satisfied_numbers(&flagged),
+        vec![(2, 0), (2, 1), (2, 2), (3, 2)]
+    );
+
+    // A wrong flag satisfies a number just the same
+    let wrong = change_flag(state, (1, 0), Flag::Sure);
+    assert_eq!(satisfied_numbers(&wrong), vec![(2, 0), (2, 1)]);
+}
+
+#[test]
+fn a_board_without_mines_is_won_by_its_first_click() {
+    let state = initial_state_seeded(4, 3, 0, 1);
+    assert_eq!(state.status, GameStatus::InProgress);
+    let state = open_cell(state, (3, 2));
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn a_board_of_only_mines_can_only_be_lost() {
+    assert_eq!(
+        validate_params(1, 1, 1),
+        Err(MineError::TooManyMines { mines: 1, cells: 1 })
+    );
+    let state = GameState::from_mines(1, 1, &[(0, 0)]);
+    assert_eq!(state.status, GameStatus::InProgress);
+    // Nothing is left to open, and flags don't win it either
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    assert_eq!(state.status, GameStatus::InProgress);
+    let state = change_flag(state, (0, 0), Flag::Unflagged);
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.status, GameStatus::Lost);
+
+    let sparse = SparseState::from_mines(1, 1, &[(0, 0)]).change_flag((0, 0), Flag::Sure);
+    assert_eq!(sparse.status, GameStatus::InProgress);
+}
+
+#[test]
+fn a_correct_flag_forces_the_rest_of_the_board_open() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    assert_eq!(forced_open_count(&state), 0);
+
+    let flagged = change_flag(state.clone(), (1, 1), Flag::Sure);
+    assert_eq!(forced_open_count(&flagged), 8);
+    // Nothing was opened for real
+    assert_eq!(
+        flagged.grid[(0, 0)].state,
+        CellState::Unopened(Flag::Unflagged)
+    );
+
+    // A wrong flag leads the first chord onto the mine
+    let wrong = change_flag(state, (1, 0), Flag::Sure);
+    assert_eq!(forced_open_count(&wrong), 0);
+}
+
+#[test]
+fn walls_of_a_cross_neither_count_nor_block_a_win() {
+    // . * .
+    // 1 1 1
+    // . . .
+    let mask = vec![
+        vec![false, true, false],
+        vec![true, true, true],
+        vec![false, true, false],
+    ];
+    let state = GameState::from_mines_masked(3, 3, &[(1, 0)], Some(mask)).unwrap();
+    for point in [(0, 1), (1, 1), (2, 1)] {
+        assert_eq!(
+            state.grid[point].cell_type,
+            CellType::Empty { adjacent_mines: 1 }
+        );
+    }
+    assert_eq!(
+        state.grid[(1, 2)].cell_type,
+        CellType::Empty { adjacent_mines: 0 }
+    );
+    // The corners are never opened by the player
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.opens, 0);
+
+    let state = open_cell(state, (1, 2));
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+// . 1 *
+// Won by opening the left cell, with an Unsure flag left on the mine
+fn won_with(flags: WinFlags) -> GameState {
+    let mut state = GameState::from_mines(3, 1, &[(2, 0)]);
+    state.rules.flag_mines_on_win = flags;
+    let state = change_flag(state, (2, 0), Flag::Unsure);
+    let state = open_cell(state, (0, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    state
+}
+
+#[test]
+fn a_win_leaves_the_flags_alone_by_default() {
+    assert_eq!(Rules::default().flag_mines_on_win, WinFlags::Keep);
+    let state = won_with(WinFlags::Keep);
+    assert_eq!(state.grid[(2, 0)].state, CellState::Unopened(Flag::Unsure));
+}
+
+#[test]
+fn flag_mines_on_win_flags_every_mine_sure() {
+    let state = won_with(WinFlags::FlagMines);
+    assert_eq!(state.grid[(2, 0)].state, CellState::Unopened(Flag::Sure));
+}
+
+#[test]
+fn smart_click_opens_closed_cells() {
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    assert_eq!(smart_click(state.clone(), (3, 0)), open_cell(state, (3, 0)));
+}
+
+#[test]
+fn smart_click_chords_only_satisfied_numbers() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    assert_eq!(smart_click(state.clone(), (2, 0)), state);
+
+    let flagged = change_flag(state.clone(), (1, 1), Flag::Sure);
+    let clicked = smart_click(flagged.clone(), (2, 0));
+    assert_eq!(clicked.grid[(1, 0)].state, CellState::Opened);
+    assert_eq!(clicked, chord(flagged, (2, 0)));
+
+    // A chord on a wrong flag loses like chord does
+    let wrong = change_flag(state, (1, 0), Flag::Sure);
+    assert_eq!(smart_click(wrong, (2, 0)).status, GameStatus::Lost);
+}
+
+#[cfg(feature = "assist")]
+#[test]
+fn winning_moves_are_the_clicks_that_finish_the_board() {
+    // * 1 .
+    let state = GameState::from_mines(3, 1, &[(0, 0)]);
+    // Only the zero's cascade clears the board in one click
+    assert_eq!(winning_moves(&state), vec![(2, 0)]);
+
+    let state = open_cell(state, (1, 0));
+    assert_eq!(winning_moves(&state), vec![(2, 0)]);
+    let state = open_cell(state, (2, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(winning_moves(&state), Vec::new());
+}
+
+#[cfg(feature = "assist")]
+#[test]
+fn under_open_all_safe_a_flag_never_changes_the_status() {
+    let state = under(WinCondition::OpenAllSafe);
+    let state = open_cell(state, (1, 1));
+    for flag in [Flag::Sure, Flag::Unsure] {
+        assert_eq!(
+            status_if_flagged(&state, (0, 0), flag),
+            GameStatus::InProgress
+        );
+    }
+}
+
+#[cfg(feature = "assist")]
+#[test]
+fn under_flag_all_mines_the_last_flag_can_win() {
+    let state = open_cell(under(WinCondition::FlagAllMines), (2, 2));
+    let before = state.clone();
+    assert_eq!(
+        status_if_flagged(&state, (0, 0), Flag::Sure),
+        GameStatus::Won
+    );
+    assert_eq!(
+        status_if_flagged(&state, (0, 0), Flag::Unsure),
+        GameStatus::InProgress
+    );
+    assert_eq!(state, before);
+}
+
+#[test]
+fn trusted_flags_open_what_they_prove_safe() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    // Nothing to go on without a flag
+    assert_eq!(open_flag_implied_safe(state.clone()), state);
+
+    let trusted = open_flag_implied_safe(change_flag(state.clone(), (1, 1), Flag::Sure));
+    assert_eq!(trusted.status, GameStatus::Won);
+    assert_eq!(trusted.safe_cells_remaining(), 0);
+
+    let wrong = open_flag_implied_safe(change_flag(state, (1, 0), Flag::Sure));
+    assert_eq!(wrong.status, GameStatus::Lost);
+    assert_eq!(wrong.lost_at, Some((1, 1)));
+}
+
+#[cfg(feature = "assist")]
+#[test]
+fn flags_needed_counts_only_the_right_flags() {
+    // * 1 * 1 * 1 .
+    let state = GameState::from_mines(7, 1, &[(0, 0), (2, 0), (4, 0)]);
+    assert_eq!(flags_needed(&state), 3);
+
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = change_flag(state, (6, 0), Flag::Sure);
+    let state = change_flag(state, (2, 0), Flag::Unsure);
+    assert_eq!(flags_needed(&state), 2);
+    assert_eq!(state.remaining_mines(), 1);
+}
+
+#[test]
+fn the_win_threshold_wins_before_the_board_is_cleared() {
+    // 0 1 * 1 0, the first click opens two of the four safe cells
+    let with_threshold = |win_threshold| {
+        let mut state = GameState::from_mines(5, 1, &[(2, 0)]);
+        state.rules.win_threshold = win_threshold;
+        state
+    };
+    assert_eq!(
+        open_cell(with_threshold(0.5), (0, 0)).status,
+        GameStatus::Won
+    );
+    assert_eq!(
+        open_cell(with_threshold(0.75), (0, 0)).status,
+        GameStatus::InProgress
+    );
+
+    // Nothing opened is never enough, however low the threshold
+    for win_threshold in [0.0, -1.0] {
+        let flagged = change_flag(with_threshold(win_threshold), (2, 0), Flag::Sure);
+        assert_eq!(flagged.status, GameStatus::InProgress);
+        assert_eq!(open_cell(flagged, (0, 0)).status, GameStatus::Won);
+    }
+
+    // NaN and thresholds above 1.0 ask for every safe cell
+    for win_threshold in [f64::NAN, 2.0] {
+        let state = open_cell(with_threshold(win_threshold), (0, 0));
+        assert_eq!(state.status, GameStatus::InProgress);
+        assert_eq!(open_cell(state, (4, 0)).status, GameStatus::Won);
+    }
+}
@@ -0,0 +1,30 @@
+use rusty_mines::prelude::*;
+
+#[test]
+fn prelude_plays_a_short_game() {
+    // A single mine in the corner, so one click on the far corner opens the rest
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    assert_eq!(state.grid[(0, 0)].state, CellState::Unopened(Flag::Sure));
+
+    let state = open_cell(state, (2, 2));
+    assert_eq!(
+        state.grid[(1, 1)].cell_type,
+        CellType::Empty { adjacent_mines: 1 }
+    );
+    assert_eq!(state.status, GameStatus::Won);
+}
+
+#[test]
+fn prelude_opens_a_safe_cell_of_a_preset() {
+    let (width, height, mines) = Difficulty::Beginner.params();
+    let state = initial_state_seeded(width, height, mines, 7);
+    let safe: Point = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .find(|&point| state.grid[point].cell_type != CellType::Mine)
+        .unwrap();
+
+    let state = open_cell(state, safe);
+    assert_eq!(state.grid[safe].state, CellState::Opened);
+    assert_ne!(state.status, GameStatus::Lost);
+}
@@ -0,0 +1,96 @@
+use rusty_mines::*;
+
+#[test]
+fn pretty_shows_the_view_and_the_solution() {
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = [(1, 0), (2, 0), (1, 1), (2, 1)]
+        .into_iter()
+        .fold(state, open_cell);
+    assert_eq!(
+        state.pretty(),
+        "view:\n  .1 \n  .1 \nsolution:\n  *1 \n  11 \n"
+    );
+}
+
+#[test]
+fn debug_output_is_left_as_derived() {
+    let state = GameState::empty(1, 1);
+    assert!(format!("{:?}", state).starts_with("GameState {"));
+}
+
+#[test]
+fn render_into_reuses_the_buffer() {
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = open_cell(state, (2, 1));
+    let mut buf = "left over from the last frame".to_string();
+    state.render_into(&mut buf);
+    assert_eq!(buf, state.render());
+    state.render_into(&mut buf);
+    assert_eq!(buf, state.render());
+    assert_eq!(buf, ".1 \n.1 \n");
+}
+
+#[test]
+fn wide_glyphs_keep_the_columns_aligned() {
+    let style = RenderStyle {
+        closed: "\u{2B1C}".to_string(),
+        flag: "\u{1F6A9}".to_string(),
+        ..RenderStyle::default()
+    };
+    // * 1 .
+    let state = GameState::from_mines(3, 1, &[(0, 0)]);
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    let state = open_cell(state, (1, 0));
+    // Every cell takes two columns, the narrow 1 padded with a space
+    assert_eq!(state.render_styled(&style), "\u{1F6A9}1 \u{2B1C}\n");
+
+    let state = open_cell(state, (2, 0));
+    assert_eq!(state.render_styled(&style), "\u{1F6A9}1   \n");
+}
+
+#[test]
+fn a_bottom_left_origin_mirrors_the_rows() {
+    // * 1 .
+    // 1 1 .
+    // . . .
+    let state = GameState::from_mines(3, 3, &[(0, 0)]);
+    let state = open_cell(state, (1, 1));
+    let top_left = state.render_with_origin(Origin::TopLeft);
+    let bottom_left = state.render_with_origin(Origin::BottomLeft);
+    assert_eq!(top_left, state.render());
+    assert_eq!(top_left, "...\n.1.\n...\n");
+    assert_eq!(
+        bottom_left.lines().collect::<Vec<&str>>(),
+        top_left.lines().rev().collect::<Vec<&str>>()
+    );
+
+    let mut buf = String::new();
+    state.render_into_with_origin(&mut buf, Origin::BottomLeft);
+    assert_eq!(buf, bottom_left);
+    assert_eq!(
+        state.render_styled(&RenderStyle {
+            origin: Origin::BottomLeft,
+            ..RenderStyle::default()
+        }),
+        bottom_left
+    );
+}
+
+#[test]
+fn rc_helpers_follow_the_origin() {
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    assert_eq!(state.point_at_rc(0, 0, Origin::TopLeft), Some((0, 0)));
+    assert_eq!(state.point_at_rc(0, 0, Origin::BottomLeft), Some((0, 1)));
+    assert_eq!(state.point_at_rc(2, 0, Origin::BottomLeft), None);
+    assert_eq!(
+        state.view_at_rc(1, 2, Origin::BottomLeft),
+        Some(CellView::Closed(Flag::Unflagged))
+    );
+}
+
+#[test]
+fn display_draws_the_player_view() {
+    let state = open_cell(GameState::from_mines(3, 2, &[(0, 0)]), (2, 1));
+    assert_eq!(state.to_string(), state.render());
+    assert_eq!(format!("{}", state), ".1 \n.1 \n");
+}
@@ -0,0 +1,250 @@
+use rusty_mines::*;
+
+#[test]
+fn an_out_of_bounds_move_names_its_index() {
+    let moves = [Move::Flag((0, 0), Flag::Sure), Move::Open((9, 0))];
+    assert_eq!(
+        replay(7, 9, 9, 10, &moves).unwrap_err(),
+        ReplayError::OutOfBounds {
+            index: 1,
+            point: (9, 0)
+        }
+    );
+}
+
+#[test]
+fn a_chord_on_a_closed_cell_is_rejected() {
+    let moves = [Move::Chord((4, 4))];
+    assert_eq!(
+        replay(7, 9, 9, 10, &moves).unwrap_err(),
+        ReplayError::NotChordable {
+            index: 0,
+            point: (4, 4)
+        }
+    );
+}
+
+// Opens the safe cells of the seeded board in row order, skipping the ones
+// an earlier cascade already opened
+fn winning_moves(seed: u64, width: usize, height: usize, mines: usize) -> Vec<Move> {
+    let mut state = initial_state_seeded(width, height, mines, seed);
+    let mut moves = Vec::new();
+    for point in state.grid.points().collect::<Vec<Point>>() {
+        if state.grid[point].cell_type != CellType::Mine
+            && state.grid[point].state != CellState::Opened
+        {
+            moves.push(Move::Open(point));
+            state = open_cell(state, point);
+        }
+    }
+    assert_eq!(state.status, GameStatus::Won);
+    moves
+}
+
+#[test]
+fn verify_win_accepts_only_a_finished_win() {
+    let moves = winning_moves(11, 9, 9, 10);
+    assert!(verify_win(11, 9, 9, 10, &moves));
+    assert!(!verify_win(11, 9, 9, 10, &moves[..moves.len() - 1]));
+    // The same clicks on another board don't win it
+    assert!(!verify_win(12, 9, 9, 10, &moves));
+}
+
+#[test]
+fn a_script_plays_like_the_calls_it_names() {
+    let script = "seed 42\nnew 9 9 10\n# comments and blank lines are skipped\n\nopen 4 4\nflag 0 0\nunsure 8 8\n";
+    let expected = initial_state_seeded(9, 9, 10, 42);
+    let expected = open_cell(expected, (4, 4));
+    let expected = change_flag(expected, (0, 0), Flag::Sure);
+    let expected = change_flag(expected, (8, 8), Flag::Unsure);
+    assert_eq!(run_script(script).unwrap(), expected);
+}
+
+#[test]
+fn a_bad_script_line_is_reported_by_number() {
+    let error = |script: &str| match run_script(script) {
+        Err(MineError::InvalidScript { line, .. }) => line,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(error("new 3 3 1\nopen 1\n"), 2);
+    assert_eq!(error("new 3 3 1\n\ndance 1 1\n"), 3);
+    assert_eq!(error("open 0 0\n"), 1);
+    assert_eq!(error("new 3 3 1\nopen 3 0\n"), 2);
+    assert_eq!(error("seed x\n"), 1);
+}
+
+fn replay_log(seed: u64, moves: &[Move]) -> GameState {
+    replay(seed, 16, 16, 40, moves).unwrap()
+}
+
+#[test]
+fn state_at_scrubs_from_the_fresh_board_to_the_end() {
+    let moves = winning_moves(5, 16, 16, 40);
+    // Long enough to cross a few snapshots
+    assert!(moves.len() > 64);
+    let replay = Replay::new(5, 16, 16, 40, moves.clone()).unwrap();
+    assert_eq!(replay.len(), moves.len());
+
+    assert_eq!(
+        replay.state_at(0).unwrap(),
+        initial_state_seeded(16, 16, 40, 5)
+    );
+    assert_eq!(replay.state_at(moves.len()).unwrap(), replay_log(5, &moves));
+    for index in [1, 31, 32, 33, 64, moves.len() - 1] {
+        assert_eq!(
+            replay.state_at(index).unwrap(),
+            replay_log(5, &moves[..index]),
+            "move {}",
+            index
+        );
+    }
+    assert_eq!(
+        replay.state_at(moves.len() + 1),
+        Err(ReplayError::PastEnd {
+            index: moves.len() + 1,
+            moves: moves.len()
+        })
+    );
+}
+
+#[test]
+fn an_exported_replay_imports_equal() {
+    let mut moves = winning_moves(3, 9, 9, 10);
+    moves.insert(1, Move::Flag((8, 8), Flag::Unsure));
+    let replay = Replay::new(3, 9, 9, 10, moves).unwrap();
+    let bytes = export_replay(&replay).unwrap();
+    assert_eq!(&bytes[..4], b"RMRP");
+    assert_eq!(import_replay(&bytes).unwrap(), replay);
+}
+
+fn header(width: u32, height: u32, mines: u32) -> Vec<u8> {
+    let mut bytes = b"RMRP\x01".to_vec();
+    bytes.extend_from_slice(&7u64.to_le_bytes());
+    for value in [width, height, mines] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn import_refuses_a_header_the_bytes_cannot_back() {
+    // Claims four billion mines with nothing after the header
+    let bytes = header(u32::MAX, u32::MAX, u32::MAX - 1);
+    assert!(matches!(
+        import_replay(&bytes),
+        Err(ReplayError::InvalidData { offset: 13, .. })
+    ));
+
+    // A board within limits whose mines are missing
+    let bytes = header(1000, 1000, 500_000);
+    assert!(matches!(
+        import_replay(&bytes),
+        Err(ReplayError::InvalidData { offset, .. }) if offset == bytes.len()
+    ));
+}
+
+#[test]
+fn export_refuses_values_past_u32() {
+    let mut replay = Replay::new(3, 9, 9, 10, Vec::new()).unwrap();
+    replay.width = u32::MAX as usize + 1;
+    assert_eq!(
+        export_replay(&replay),
+        Err(ReplayError::TooLarge(format!(
+            "width {}",
+            u32::MAX as usize + 1
+        )))
+    );
+}
+
+// A game away from the defaults: the first click lands on a mine that is
+// moved away, with a spare life and a move budget
+fn logged_game() -> Game {
+    let difficulty = Difficulty::Custom {
+        width: 9,
+        height: 9,
+        mines: 10,
+    };
+    let mut game = Game::from_difficulty(difficulty, Some(11));
+    game.safe_first_click = true;
+    game.state.lives = 2;
+    game.state.rules.max_opens = Some(60);
+    game.state.rules.cascade_rule = CascadeRule::OpenOneMore;
+    game
+}
+
+#[test]
+fn a_move_log_replays_to_the_game_it_recorded() {
+    let mut game = logged_game();
+    let mine = game.state.mine_positions()[0];
+    let mut log = MoveLogWriter::new(Vec::new(), &game).unwrap();
+    let mut moves = vec![Move::Open(mine), Move::Flag((8, 8), Flag::Unsure)];
+    for &action in &moves {
+        log.append(action).unwrap();
+        game.play(action);
+    }
+    assert_ne!(
+        game.state.mine_positions(),
+        logged_game().state.mine_positions()
+    );
+    while game.state.status == GameStatus::InProgress {
+        let point = game
+            .state
+            .grid
+            .points()
+            .find(|&point| {
+                game.state.grid[point].cell_type != CellType::Mine
+                    && matches!(game.state.grid[point].state, CellState::Unopened(_))
+            })
+            .unwrap();
+        log.append(Move::Open(point)).unwrap();
+        game.play(Move::Open(point));
+        moves.push(Move::Open(point));
+    }
+    assert_eq!(game.state.status, GameStatus::Won);
+
+    let replayed = replay_from_log(&log.into_inner()[..]).unwrap();
+    assert_eq!(replayed.state, game.state);
+    assert_eq!(save_game(&replayed), save_game(&game));
+}
+
+#[test]
+fn a_move_cut_off_by_a_crash_is_dropped() {
+    let header = MoveLogWriter::new(Vec::new(), &logged_game())
+        .unwrap()
+        .into_inner();
+    let header = String::from_utf8(header).unwrap();
+    let log = format!("{}flag 0 0\nopen 4", header);
+    let replayed = replay_from_log(log.as_bytes()).unwrap();
+    let mut expected = logged_game();
+    expected.flag((0, 0), Flag::Sure);
+    assert_eq!(replayed.state, expected.state);
+
+    let log = format!("{}flag 0 0\npoke 1 1\n", header);
+    assert_eq!(
+        replay_from_log(log.as_bytes()).unwrap_err(),
+        ReplayError::InvalidLog {
+            line: header.lines().count() + 2,
+            reason: "unknown move poke".to_string()
+        }
+    );
+}
+
+#[test]
+fn losing_context_is_the_board_before_the_fatal_click() {
+    let state = initial_state_seeded(9, 9, 10, 11);
+    let mine = state.mine_positions()[2];
+    let mut moves = winning_moves(11, 9, 9, 10);
+    moves.truncate(3);
+    moves.push(Move::Open(mine));
+    let replay = Replay::new(11, 9, 9, 10, moves).unwrap();
+
+    let (before, point) = replay.losing_context().unwrap();
+    assert_eq!(point, mine);
+    assert_eq!(before.status, GameStatus::InProgress);
+    assert_eq!(before, replay.state_at(3).unwrap());
+    assert_eq!(open_cell(before, point).status, GameStatus::Lost);
+
+    // A game that wasn't lost has none
+    let replay = Replay::new(11, 9, 9, 10, winning_moves(11, 9, 9, 10)).unwrap();
+    assert_eq!(replay.losing_context(), None);
+}
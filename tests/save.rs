@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rusty_mines::*;
+
+#[test]
+fn a_version_1_save_loads_with_the_defaults() {
+    let value = parse_save("rusty-mines 1\nstatus in-progress\nboard\noo?\n..*\n").unwrap();
+    assert_eq!(value.version, 1);
+    let state = migrate(value).unwrap();
+
+    let mut expected = GameState::from_mines(3, 2, &[(2, 1)]);
+    expected.rules = state.rules;
+    expected.grid.open((0, 0));
+    expected.grid.open((1, 0));
+    expected.grid[(2, 0)].state = CellState::Unopened(Flag::Unsure);
+    assert_eq!(state, expected);
+    assert_eq!(state.lives, 1);
+    // Saves that old opened Sure flags and flagged the mines on a win
+    assert_eq!(
+        state.rules,
+        Rules {
+            open_flag_policy: OpenFlagPolicy::OpenAll,
+            flag_mines_on_win: WinFlags::FlagMines,
+            ..Rules::default()
+        }
+    );
+}
+
+#[test]
+fn a_save_from_a_newer_version_is_rejected() {
+    let save = format!("rusty-mines {}\nstatus won\nboard\no\n", SAVE_VERSION + 1);
+    assert_eq!(
+        load_state(&save),
+        Err(MineError::UnsupportedVersion {
+            version: SAVE_VERSION + 1,
+            supported: SAVE_VERSION
+        })
+    );
+}
+
+#[test]
+fn a_puzzle_code_gives_back_a_fresh_board_with_the_layout() {
+    for seed in 0..10 {
+        let state = initial_state_seeded(9 + seed as usize, 7, 12, seed);
+        let played = change_flag(state.clone(), (0, 0), Flag::Sure);
+        let played = open_cell(played, (4, 3));
+        let code = puzzle_code(&played);
+        assert!(code.len() < save_state(&played).len());
+
+        let decoded = from_puzzle_code(&code).unwrap();
+        assert_eq!(decoded, state, "seed {}", seed);
+        assert_eq!(decoded.status, GameStatus::InProgress);
+    }
+}
+
+#[test]
+fn broken_puzzle_codes_are_rejected() {
+    let code = puzzle_code(&GameState::from_mines(4, 3, &[(1, 1)]));
+    for broken in ["", "4x3", "4by3.AA", "4x3.A", "9x9.AQA", "4x3.!!"] {
+        assert!(
+            matches!(
+                from_puzzle_code(broken),
+                Err(MineError::InvalidPuzzleCode(_))
+            ),
+            "{}",
+            broken
+        );
+    }
+    assert_eq!(
+        from_puzzle_code(&code).unwrap().mine_positions(),
+        vec![(1, 1)]
+    );
+}
+
+// Seeded boards, every other one on a diamond, each fresh, flagged and
+// opened, in lives mode with a mine opened, and lost
+fn varied_states() -> Vec<GameState> {
+    let diamond = (0..7)
+        .map(|y: usize| {
+            (0..9)
+                .map(|x: usize| x.abs_diff(4) + y.abs_diff(3) <= 4)
+                .collect()
+        })
+        .collect::<Vec<Vec<bool>>>();
+    let mut states = Vec::new();
+    for seed in 0..12 {
+        let fresh = match seed % 2 {
+            0 => initial_state_seeded(9, 7, 10, seed),
+            _ => initial_state_masked(
+                9,
+                7,
+                10,
+                Some(diamond.clone()),
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap(),
+        };
+        let mine = fresh.mine_positions()[0];
+        let safe = fresh
+            .grid
+            .points()
+            .find(|&point| {
+                fresh.grid[point].cell_type != CellType::Mine && !fresh.grid.is_wall(point)
+            })
+            .unwrap();
+
+        let flagged = change_flag(fresh.clone(), mine, Flag::Sure);
+        let flagged = open_cell(flagged, safe);
+        let flagged = change_flag(flagged, fresh.mine_positions()[1], Flag::Unsure);
+        let mut lives = fresh.clone();
+        lives.lives = 3;
+        let lives = open_cell(open_cell(lives, mine), safe);
+        assert_eq!((lives.lives, lives.status), (2, GameStatus::InProgress));
+        let lost = open_cell(fresh.clone(), mine);
+        // Thresholds out of range play as 1.0, so they load back as that
+        let mut threshold = fresh.clone();
+        threshold.rules.win_threshold = match seed % 3 {
+            0 => f64::NAN,
+            1 => 1.5,
+            _ => 0.5,
+        };
+        states.extend([fresh, flagged, lives, lost, threshold]);
+    }
+    states
+}
+
+#[test]
+fn every_state_loads_back_equal() {
+    for state in varied_states() {
+        let text = save_state(&state);
+        assert_eq!(load_state(&text).unwrap(), state, "{}", text);
+    }
+}
+
+#[test]
+fn every_grid_unpacks_equal() {
+    for state in varied_states() {
+        assert_eq!(
+            state.grid.pack().unpack(),
+            state.grid,
+            "{}",
+            save_state(&state)
+        );
+    }
+}
+
+#[test]
+fn every_layout_comes_back_from_its_puzzle_code() {
+    // Codes keep neither the play nor the walls
+    for state in varied_states()
+        .into_iter()
+        .filter(|state| state.grid.mask().is_none())
+    {
+        let fresh = GameState::from_mines(9, 7, &state.mine_positions());
+        assert_eq!(from_puzzle_code(&puzzle_code(&state)).unwrap(), fresh);
+    }
+}
+
+#[test]
+fn a_rendered_board_reads_back_the_same() {
+    // A row of mines, flagged below the numbers, with the board behind them
+    // left closed
+    let mines = (0..5).map(|x| (x, 1)).collect::<Vec<Point>>();
+    let mut state = GameState::from_mines(5, 4, &mines);
+    for x in 0..5 {
+        state = open_cell(state, (x, 0));
+        state = change_flag(state, (x, 1), Flag::Sure);
+    }
+    let text = state.render();
+    assert_eq!(text, "23332\nFFFFF\n.....\n.....\n");
+
+    let imported = from_render(&text).unwrap();
+    assert_eq!(imported.grid, state.grid);
+    assert_eq!(imported.status, GameStatus::InProgress);
+    assert_eq!(imported.render(), text);
+}
+
+#[test]
+fn a_render_the_numbers_leave_open_is_rejected() {
+    // Either closed cell could be the mine
+    assert!(matches!(
+        from_render(".1 \n.1 \n"),
+        Err(MineError::InvalidRender { line: 1, .. })
+    ));
+}
+
+#[test]
+fn a_turned_board_has_the_same_canonical_fingerprint() {
+    for seed in 0..10 {
+        let state = initial_state_seeded(8, 8, 12, seed);
+        let fingerprint = canonical_fingerprint(&state);
+        let turned = state.rotate90();
+        assert_eq!(canonical_fingerprint(&turned), fingerprint);
+        assert_eq!(canonical_fingerprint(&turned.rotate90()), fingerprint);
+        assert_eq!(canonical_fingerprint(&state.flip_horizontal()), fingerprint);
+        assert_ne!(
+            canonical_fingerprint(&initial_state_seeded(8, 8, 12, seed + 100)),
+            fingerprint
+        );
+
+        // Boards that aren't square can still be turned half way and mirrored
+        let wide = initial_state_seeded(9, 5, 8, seed);
+        let fingerprint = canonical_fingerprint(&wide);
+        assert_eq!(
+            canonical_fingerprint(&wide.rotate90().rotate90()),
+            fingerprint
+        );
+        assert_eq!(canonical_fingerprint(&wide.flip_vertical()), fingerprint);
+    }
+    // Nothing to turn, but nothing to panic over either
+    canonical_fingerprint(&GameState::empty(0, 0));
+}
@@ -0,0 +1,31 @@
+use rusty_mines::*;
+
+// The solver's reasoning on a fixed board, one guess included. A change here
+// means the solver now plays the board differently, which may be fine but
+// should be looked at before updating the expected trace.
+const EXPECTED: &str = "\
+open (2, 2): 1 opened
+guess (2, 3) at 12.5%
+open (2, 3): 25 opened
+(1, 0) is a mine because (2, 0) shows 2 and every unknown neighbor left must be a mine
+(1, 1) is a mine because (2, 0) shows 2 and every unknown neighbor left must be a mine
+(5, 3) is a mine because (4, 2) shows 1 and every unknown neighbor left must be a mine
+(2, 5) is a mine because (1, 4) shows 1 and every unknown neighbor left must be a mine
+(3, 5) is a mine because (2, 4) shows 2 and every unknown neighbor left must be a mine
+(4, 5) is a mine because (3, 4) shows 3 and every unknown neighbor left must be a mine
+(0, 1) is safe because (0, 2) shows 1 and all its mines are accounted for
+(5, 4) is safe because (4, 3) shows 1 and all its mines are accounted for
+(5, 5) is safe because (4, 4) shows 3 and all its mines are accounted for
+open (0, 1): 1 opened
+open (5, 4): 1 opened
+open (5, 5): 1 opened
+(0, 0) is safe because (0, 1) shows 2 and all its mines are accounted for
+open (0, 0): 1 opened
+Won
+";
+
+#[test]
+fn solve_trace_matches_snapshot() {
+    let state = initial_state_seeded(6, 6, 6, 27);
+    assert_eq!(solve_trace(&state, (2, 2)), EXPECTED);
+}
@@ -0,0 +1,691 @@
+use rusty_mines::*;
+use std::collections::HashMap;
+
+#[test]
+fn adjacency_histogram_counts_the_numbers_of_safe_cells() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    assert_eq!(adjacency_histogram(&state), [4, 9, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn board_3bv_counts_openings_and_lone_numbers() {
+    // Two openings, the zeros on the right edge and in the bottom left
+    // corner, and the three numbers in the top left that neither reaches
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    assert_eq!(board_3bv(&state), 5);
+    assert_eq!(minimal_winning_clicks(&state).len(), 5);
+}
+
+#[test]
+fn minimal_winning_clicks_clear_the_board() {
+    for seed in 0..10 {
+        let state = initial_state_seeded(9, 9, 10, seed);
+        let clicks = minimal_winning_clicks(&state);
+        let played = clicks
+            .iter()
+            .fold(state.clone(), |state, &point| open_cell(state, point));
+        assert_eq!(played.status, GameStatus::Won, "seed {}", seed);
+        assert_eq!(clicks.len() as u32, board_3bv(&state));
+    }
+}
+
+#[test]
+fn isolated_safe_regions_finds_a_walled_in_cell() {
+    // The bottom right corner is fenced off by three mines, so no number
+    // the first click can reach says anything about it
+    let state = GameState::from_mines(5, 5, &[(3, 3), (4, 3), (3, 4)]);
+    assert_eq!(isolated_safe_regions(&state, (0, 0)), vec![vec![(4, 4)]]);
+}
+
+#[test]
+fn isolated_safe_regions_is_empty_when_logic_clears_the_board() {
+    let state = GameState::from_mines(5, 3, &[(0, 0), (4, 2)]);
+    assert!(is_solvable_without_guessing(&state, (2, 1)));
+    assert_eq!(
+        isolated_safe_regions(&state, (2, 1)),
+        Vec::<Vec<Point>>::new()
+    );
+}
+
+#[test]
+fn cascade_sizes_counts_what_each_click_opens() {
+    // . 1 * 1
+    // . 1 1 1
+    let state = GameState::from_mines(4, 2, &[(2, 0)]);
+    let before = state.clone();
+    assert_eq!(
+        cascade_sizes(&state),
+        vec![vec![4, 1, 0, 1], vec![4, 1, 1, 1]]
+    );
+    assert_eq!(state, before);
+
+    // Opened cells have nothing left to open
+    let state = open_cell(state, (0, 0));
+    assert_eq!(
+        cascade_sizes(&state),
+        vec![vec![0, 0, 0, 1], vec![0, 0, 1, 1]]
+    );
+}
+
+#[test]
+fn guess_free_openings_keeps_only_the_fair_starts() {
+    // * 1 .
+    // The number alone can't tell which side the mine is on, the zero can
+    let state = GameState::from_mines(3, 1, &[(0, 0)]);
+    assert_eq!(guess_free_openings(&state), vec![(2, 0)]);
+
+    for seed in 0..5 {
+        let state = initial_state_seeded(6, 6, 6, seed);
+        let openings = guess_free_openings(&state);
+        for point in state.grid.points() {
+            let fair = state.grid[point].cell_type != CellType::Mine
+                && is_solvable_without_guessing(&state, point);
+            assert_eq!(openings.contains(&point), fair, "seed {} {:?}", seed, point);
+        }
+    }
+}
+
+#[test]
+fn probability_grid_covers_only_the_closed_unflagged_cells() {
+    let state = initial_state_seeded(9, 9, 10, 2);
+    let click = state
+        .grid
+        .points()
+        .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        .unwrap();
+    let state = open_cell(state, click);
+    let flagged = state
+        .grid
+        .points()
+        .find(|&point| state.grid[point].state == CellState::Unopened(Flag::Unflagged))
+        .unwrap();
+    let state = change_flag(state, flagged, Flag::Unsure);
+
+    let grid = probability_grid(&state);
+    for (x, y) in state.grid.points() {
+        match state.grid[(x, y)].state {
+            CellState::Unopened(Flag::Unflagged) => {
+                let chance = grid[y][x].unwrap();
+                assert!((0.0..=1.0).contains(&chance), "{:?} {}", (x, y), chance);
+            }
+            _ => assert_eq!(grid[y][x], None, "{:?}", (x, y)),
+        }
+    }
+}
+
+#[test]
+fn advance_forced_finishes_a_guess_free_board() {
+    // . . . . .
+    // . . 1 1 1
+    // . . 1 * 1
+    let state = GameState::from_mines(5, 3, &[(3, 2)]);
+    assert!(is_solvable_without_guessing(&state, (0, 0)));
+    let state = advance_forced(open_cell(state, (0, 0)));
+    assert_eq!(state.status, GameStatus::Won);
+
+    // Nothing is forced before the first click
+    let state = initial_state_seeded(9, 9, 10, 3);
+    assert_eq!(advance_forced(state.clone()), state);
+}
+
+#[test]
+fn expected_open_value_favors_the_cell_that_reveals_more() {
+    // * 1 . . .
+    // Either side of the 1 is a coin flip, but only the right one can be a
+    // zero that opens the rest of the row
+    let state = GameState::from_mines(5, 1, &[(0, 0)]);
+    let state = open_cell(state, (1, 0));
+    let probabilities = mine_probabilities(&state);
+    assert!((probabilities[&(0, 0)] - probabilities[&(2, 0)]).abs() < 1e-9);
+
+    let values = expected_open_value(&state);
+    assert_eq!(values.len(), 2);
+    assert!(values[&(2, 0)] > values[&(0, 0)]);
+}
+
+#[test]
+fn classify_flags_judges_each_flag_by_what_the_numbers_prove() {
+    // . . . . .
+    // . . 1 1 1
+    // . . 1 * 1
+    let state = GameState::from_mines(5, 3, &[(3, 2)]);
+    let state = open_cell(state, (0, 0));
+    let state = change_flag(state, (3, 2), Flag::Sure);
+    let state = change_flag(state, (4, 2), Flag::Sure);
+    assert_eq!(
+        classify_flags(&state),
+        [
+            ((3, 2), FlagJustification::Confirmed),
+            ((4, 2), FlagJustification::Contradicted)
+        ]
+        .into()
+    );
+
+    // * 1 . . .
+    let state = GameState::from_mines(5, 1, &[(0, 0)]);
+    let state = open_cell(state, (1, 0));
+    let state = change_flag(state, (0, 0), Flag::Sure);
+    assert_eq!(
+        classify_flags(&state),
+        [((0, 0), FlagJustification::Speculative)].into()
+    );
+}
+
+#[test]
+fn deduce_mine_positions_reads_the_mines_off_the_numbers() {
+    let mut state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    for point in state.grid.points().collect::<Vec<Point>>() {
+        if state.grid[point].cell_type != CellType::Mine {
+            state.grid.open(point);
+        }
+    }
+    assert_eq!(deduce_mine_positions(&state), Some(vec![(1, 1), (3, 3)]));
+
+    // * 1 .
+    // The 1 can't tell which side its mine is on
+    let state = open_cell(GameState::from_mines(3, 1, &[(0, 0)]), (1, 0));
+    assert_eq!(deduce_mine_positions(&state), None);
+}
+
+#[test]
+fn constraint_graph_links_numbers_to_their_closed_cells() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    let graph = constraint_graph(&state);
+    assert_eq!(graph.numbers.len(), 4);
+    assert!(graph.numbers.contains(&((2, 2), 2)));
+    assert_eq!(
+        graph.cells,
+        vec![(1, 0), (1, 1), (1, 2), (1, 3), (2, 3), (3, 3)]
+    );
+    assert_eq!(graph.edges.len(), 12);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("graph constraints {\n") && dot.ends_with("}\n"));
+    assert_eq!(dot.matches(" -- ").count(), 12);
+    assert_eq!(dot.matches("shape=box").count(), 4);
+    assert_eq!(dot.matches("shape=circle").count(), 6);
+}
+
+#[test]
+fn a_column_of_mines_splits_the_safe_cells_in_two() {
+    let state = GameState::from_mines(5, 3, &[(2, 0), (2, 1), (2, 2)]);
+    assert_eq!(safe_connectivity(&state), 2);
+
+    // Diagonal steps connect, like a cascade spreading
+    let state = GameState::from_mines(3, 3, &[(1, 0), (0, 1)]);
+    assert_eq!(safe_connectivity(&state), 1);
+    assert_eq!(safe_connectivity(&GameState::empty(4, 4)), 1);
+}
+
+#[test]
+fn opened_numbers_lists_the_visible_constraints() {
+    // 1 1 1 .
+    // 1 * 1 .
+    // 1 1 2 1
+    // . . 1 *
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    assert_eq!(opened_numbers(&state), Vec::new());
+    let state = open_cell(state, (3, 0));
+    assert_eq!(
+        opened_numbers(&state),
+        vec![((2, 0), 1), ((2, 1), 1), ((2, 2), 2), ((3, 2), 1)]
+    );
+}
+
+#[test]
+fn every_deduction_points_at_a_number_that_justifies_it() {
+    let mut checked = 0;
+    for seed in 0..20 {
+        let state = initial_state_seeded(9, 9, 10, seed);
+        let Some(click) = state
+            .grid
+            .points()
+            .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        else {
+            continue;
+        };
+        let state = open_cell(state, click);
+        let (safe, mines, deductions) = deduce_all_explained(&state);
+        for deduction in deductions {
+            let is_mine = state.grid[deduction.point].cell_type == CellType::Mine;
+            assert_eq!(deduction.mine, is_mine, "seed {} {}", seed, deduction);
+            assert!(match deduction.mine {
+                true => mines.contains(&deduction.point),
+                false => safe.contains(&deduction.point),
+            });
+            let justifying = deduction
+                .using
+                .into_iter()
+                .chain([(deduction.because, deduction.shows)]);
+            for (number, shows) in justifying {
+                assert_eq!(state.grid[number].state, CellState::Opened);
+                assert_eq!(
+                    state.grid[number].cell_type,
+                    CellType::Empty {
+                        adjacent_mines: shows
+                    }
+                );
+            }
+            assert!(state
+                .grid
+                .adjacent(deduction.because)
+                .contains(&deduction.point));
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn a_deduction_explains_itself() {
+    // . . . . .
+    // . . 1 1 1
+    // . . 1 * 1
+    let state = open_cell(GameState::from_mines(5, 3, &[(3, 2)]), (0, 0));
+    let (_, _, deductions) = deduce_all_explained(&state);
+    let explained = deductions
+        .iter()
+        .map(|deduction| deduction.to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        explained,
+        vec![
+            "(3, 2) is a mine because (2, 1) shows 1 and every unknown neighbor left must be a mine",
+            "(4, 2) is safe because (3, 1) shows 1 and all its mines are accounted for"
+        ]
+    );
+}
+
+#[test]
+fn cascade_cells_are_what_open_cell_opens() {
+    for seed in 0..10 {
+        let state = initial_state_seeded(9, 9, 10, seed);
+        let state = change_flag(state, (4, 4), Flag::Sure);
+        let before = state.clone();
+        for point in state.grid.points() {
+            let cells = cascade_cells(&state, point);
+            let opened = open_cell(state.clone(), point);
+            let expected = opened
+                .grid
+                .points()
+                .filter(|&point| opened.grid[point].state == CellState::Opened)
+                .collect::<Vec<Point>>();
+            let mut sorted = cells.clone();
+            sorted.sort_unstable_by_key(|&(x, y)| (y, x));
+            match state.grid[point].cell_type {
+                CellType::Mine => assert_eq!(cells, Vec::new()),
+                CellType::Empty { .. } => {
+                    assert_eq!(sorted, expected, "seed {} {:?}", seed, point)
+                }
+            }
+        }
+        assert_eq!(state, before);
+    }
+}
+
+#[test]
+fn a_corner_claiming_eight_mines_is_a_conflict() {
+    let state = open_cell(GameState::from_mines(4, 4, &[(3, 3)]), (0, 0));
+    assert_eq!(find_conflicts(&state), Vec::new());
+
+    let mut corrupted = state.clone();
+    corrupted.grid[(0, 0)].cell_type = CellType::Empty { adjacent_mines: 8 };
+    assert_eq!(find_conflicts(&corrupted), vec![(0, 0)]);
+
+    // Every neighbor is opened and safe, so even a 1 can't be right
+    let mut corrupted = state;
+    corrupted.grid[(0, 0)].cell_type = CellType::Empty { adjacent_mines: 1 };
+    assert_eq!(find_conflicts(&corrupted), vec![(0, 0)]);
+}
+
+#[test]
+fn next_hint_opens_a_proven_safe_cell_first() {
+    // . . . . .
+    // . . 1 1 1
+    // . . 1 * 1
+    let state = open_cell(GameState::from_mines(5, 3, &[(3, 2)]), (0, 0));
+    assert_eq!(next_hint(&state), Some(Hint::OpenSafe((4, 2))));
+}
+
+#[test]
+fn next_hint_flags_a_proven_mine_when_nothing_is_safe() {
+    // * 1 . . .
+    // 3 3 1 . .
+    // * * 2 . .
+    // 3 * 2 . .
+    let state = GameState::from_mines(5, 4, &[(0, 0), (0, 2), (1, 2), (1, 3)]);
+    let state = open_cell(state, (2, 0));
+    assert_eq!(next_hint(&state), Some(Hint::FlagMine((0, 2))));
+    let state = change_flag(state, (0, 2), Flag::Sure);
+    assert_eq!(next_hint(&state), Some(Hint::FlagMine((1, 2))));
+}
+
+#[test]
+fn next_hint_guesses_once_logic_runs_out() {
+    let state = initial_state_seeded(9, 9, 10, 1);
+    let Some(Hint::BestGuess(point, chance)) = next_hint(&state) else {
+        panic!("a fresh board has nothing proven");
+    };
+    assert!(state.grid.in_bounds(point));
+    assert!((chance - 10.0 / 81.0).abs() < 1e-9);
+
+    let lost = open_cell(state.clone(), state.mine_positions()[0]);
+    assert_eq!(next_hint(&lost), None);
+}
+
+#[test]
+fn mines_far_apart_form_separate_clusters() {
+    let state = GameState::from_mines(10, 4, &[(0, 0), (1, 1), (6, 2), (7, 3), (9, 0)]);
+    assert_eq!(
+        mine_clusters(&state),
+        vec![vec![(0, 0), (1, 1)], vec![(9, 0)], vec![(6, 2), (7, 3)]]
+    );
+
+    // Two apart, a number between them touches both
+    let state = GameState::from_mines(5, 1, &[(0, 0), (2, 0)]);
+    assert_eq!(mine_clusters(&state), vec![vec![(0, 0), (2, 0)]]);
+}
+
+#[test]
+fn a_tiny_budget_still_covers_the_frontier() {
+    use std::time::Duration;
+
+    for seed in 0..5 {
+        let state = initial_state_seeded(30, 16, 99, seed);
+        let Some(click) = state
+            .grid
+            .points()
+            .find(|&point| state.grid[point].cell_type == CellType::Empty { adjacent_mines: 0 })
+        else {
+            continue;
+        };
+        let state = open_cell(state, click);
+        let estimate = mine_probabilities_budgeted(&state, Duration::ZERO);
+        for point in state.frontier() {
+            let chance = estimate[&point];
+            assert!((0.0..=1.0).contains(&chance), "seed {} {:?}", seed, point);
+        }
+
+        // Given time, it is the exact answer
+        let exact = mine_probabilities(&state);
+        let budgeted = mine_probabilities_budgeted(&state, Duration::from_secs(60));
+        assert_eq!(budgeted.len(), exact.len());
+        for (point, chance) in exact {
+            assert!((budgeted[&point] - chance).abs() < 1e-9, "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn entropy_is_zero_once_everything_is_known() {
+    // . . . . .
+    // . . 1 1 1
+    // . . 1 * 1
+    let state = open_cell(GameState::from_mines(5, 3, &[(3, 2)]), (0, 0));
+    assert!(remaining_entropy(&state).abs() < 1e-9);
+}
+
+#[test]
+fn a_coin_flip_is_worth_a_bit() {
+    // * 1 . . .
+    // The mine count clears the two cells on the far right, leaving the
+    // cells on either side of the 1 as coin flips of a bit each
+    let state = open_cell(GameState::from_mines(5, 1, &[(0, 0)]), (1, 0));
+    let entropy = remaining_entropy(&state);
+    assert!(entropy > 0.0);
+    assert!((entropy - 2.0).abs() < 1e-9, "{}", entropy);
+}
+
+#[test]
+fn local_probability_covers_only_the_frontier_near_the_point() {
+    // * 1 . . .
+    let state = GameState::from_mines(5, 1, &[(0, 0)]);
+    let state = open_cell(state, (1, 0));
+    assert_eq!(
+        local_probability(&state, (0, 0), 1),
+        HashMap::from([((0, 0), 0.5)])
+    );
+    assert_eq!(local_probability(&state, (4, 0), 1), HashMap::new());
+
+    let state = initial_state_seeded(16, 16, 40, 2);
+    let click = best_first_move(&state);
+    let state = open_cell(state, click);
+    let frontier = state.frontier();
+    for (around, radius) in [((8, 8), 2), ((0, 0), 3), ((15, 4), 5)] {
+        for point in local_probability(&state, around, radius).into_keys() {
+            assert!(frontier.contains(&point), "{:?}", point);
+            assert!(point.0.abs_diff(around.0) <= radius && point.1.abs_diff(around.1) <= radius);
+        }
+    }
+}
+
+#[test]
+fn a_fifty_fifty_is_the_guess_left() {
+    // * 1 .
+    // 1 1 .
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = open_cell(state, (2, 0));
+    assert_eq!(required_guesses(&state), vec![(0, 0), (0, 1)]);
+
+    // Logic alone clears this one
+    let state = GameState::from_mines(4, 4, &[(1, 1), (3, 3)]);
+    let state = open_cell(state, (3, 0));
+    assert_eq!(required_guesses(&state), Vec::new());
+}
+
+#[test]
+fn minimal_winning_clicks_skip_the_walls() {
+    // The wall in the top row is already open and never needs a click
+    let mask = vec![vec![true, false, true], vec![true, true, true]];
+    let state = GameState::from_mines_masked(3, 2, &[], Some(mask)).unwrap();
+    let clicks = minimal_winning_clicks(&state);
+    assert!(!clicks.contains(&(1, 0)));
+    let played = clicks
+        .iter()
+        .fold(state, |state, &point| open_cell(state, point));
+    assert_eq!(played.status, GameStatus::Won);
+}
+
+#[test]
+fn walls_are_no_safe_cells() {
+    // A wall in the middle of a mine-free board
+    let mask = vec![
+        vec![true, true, true],
+        vec![true, false, true],
+        vec![true, true, true],
+    ];
+    let state = GameState::from_mines_masked(3, 3, &[], Some(mask)).unwrap();
+    assert_eq!(safe_connectivity(&state), 1);
+    assert_eq!(adjacency_histogram(&state), [8, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+// A plus sign, the corners being walls
+fn cross() -> Vec<Vec<bool>> {
+    vec![
+        vec![false, true, false],
+        vec![true, true, true],
+        vec![false, true, false],
+    ]
+}
+
+#[test]
+fn the_trace_of_a_walled_board_starts_with_the_first_click() {
+    let state = GameState::from_mines_masked(3, 3, &[(1, 0)], Some(cross())).unwrap();
+    let trace = solve_trace(&state, (1, 2));
+    assert!(trace.starts_with("open (1, 2): 4 opened\n"), "{}", trace);
+}
+
+#[test]
+fn best_first_move_opens_into_the_big_region() {
+    // . . . . . 2 * 2 .
+    // . . . . . 3 * 3 .
+    // . . . . . 2 * 2 .
+    let state = GameState::from_mines(9, 3, &[(6, 0), (6, 1), (6, 2)]);
+    let click = best_first_move(&state);
+    assert_eq!(click, (4, 1));
+    assert_eq!(cascade_sizes(&state)[1][4], 18);
+
+    // Nothing left to open suggests the center
+    let state = open_cell(state, (0, 0));
+    let state = open_cell(state, (8, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(best_first_move(&state), (4, 1));
+    assert_eq!(best_first_move(&GameState::empty(0, 0)), (0, 0));
+}
+
+#[test]
+fn remaining_logical_moves_counts_what_the_player_left_undone() {
+    // . . . . . . . . .
+    // 1 1 1 . . . 1 1 1
+    // 1 * 1 . . . 1 * 1
+    let state = GameState::from_mines(9, 3, &[(1, 2), (7, 2)]);
+    let state = open_cell(state, (4, 0));
+    assert_eq!(remaining_logical_moves(&state), 4);
+
+    let state = change_flag(state, (1, 2), Flag::Sure);
+    // An Unsure flag doesn't settle a mine
+    let state = change_flag(state, (7, 2), Flag::Unsure);
+    assert_eq!(remaining_logical_moves(&state), 3);
+    let state = open_cell(state, (0, 2));
+    assert_eq!(remaining_logical_moves(&state), 2);
+}
+
+#[test]
+fn separate_frontiers_are_separate_components() {
+    // . . . . . . . . .
+    // 1 1 1 . . . 1 1 1
+    // 1 * 1 . . . 1 * 1
+    let state = GameState::from_mines(9, 3, &[(1, 2), (7, 2)]);
+    assert_eq!(independent_components(&state), Vec::<Vec<Point>>::new());
+    let state = open_cell(state, (4, 0));
+    assert_eq!(
+        independent_components(&state),
+        vec![vec![(0, 2), (1, 2)], vec![(7, 2), (8, 2)]]
+    );
+}
+
+#[test]
+fn a_number_with_one_mine_in_two_cells_is_half_tense() {
+    // * 1 .
+    // 1 1 .
+    let state = GameState::from_mines(3, 2, &[(0, 0)]);
+    let state = open_cell(state, (2, 0));
+    assert_eq!(
+        number_tension(&state),
+        HashMap::from([((1, 0), 0.5), ((1, 1), 0.5)])
+    );
+
+    // A Sure flag takes the mine, even a wrong one
+    let state = change_flag(state, (0, 1), Flag::Sure);
+    assert_eq!(
+        number_tension(&state),
+        HashMap::from([((1, 0), 0.0), ((1, 1), 0.0)])
+    );
+}
+
+#[test]
+fn steps_remaining_shrink_as_the_board_fills_in() {
+    let fresh = initial_state_seeded(9, 9, 10, 3);
+    assert!(estimated_steps_remaining(&fresh) > 50);
+
+    // Two mines to flag and two safe cells to open, all deducible
+    let state = GameState::from_mines(9, 3, &[(1, 2), (7, 2)]);
+    let state = open_cell(state, (4, 0));
+    assert_eq!(estimated_steps_remaining(&state), 4);
+    let state = open_cell(open_cell(state, (0, 2)), (8, 2));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(estimated_steps_remaining(&state), 0);
+}
+
+#[test]
+fn determined_cells_leave_out_the_fifty_fifty() {
+    // * 1 1 1
+    // 1 1 1 *
+    // . . 1 1
+    let state = GameState::from_mines(4, 3, &[(0, 0), (3, 1)]);
+    let state = open_cell(state, (1, 2));
+    assert_eq!(
+        determined_cells(&state),
+        HashMap::from([
+            ((0, 0), true),
+            ((1, 0), false),
+            ((2, 0), false),
+            ((3, 0), false)
+        ])
+    );
+}
+
+#[test]
+fn border_completeness_is_the_settled_share_of_the_border() {
+    // * 1 1 1
+    // 1 1 1 *
+    // . . 1 1
+    let state = GameState::from_mines(4, 3, &[(0, 0), (3, 1)]);
+    assert_eq!(border_completeness(&state), 1.0);
+    // Six cells border the numbers, and the 50/50 on the right is unsettled
+    let state = open_cell(state, (1, 2));
+    assert_eq!(border_completeness(&state), 4.0 / 6.0);
+    let state = change_flag(state, (3, 1), Flag::Sure);
+    assert_eq!(border_completeness(&state), 5.0 / 6.0);
+}
+
+#[test]
+fn largest_remaining_cascade_picks_the_bigger_region() {
+    // . 2 * 2 . . .
+    // . 3 * 3 . . .
+    // . 2 * 2 . . .
+    let state = GameState::from_mines(7, 3, &[(2, 0), (2, 1), (2, 2)]);
+    assert_eq!(largest_remaining_cascade(&state), Some((4, 0)));
+    let state = open_cell(state, (6, 2));
+    assert_eq!(largest_remaining_cascade(&state), Some((0, 0)));
+    let state = open_cell(state, (0, 2));
+    assert_eq!(largest_remaining_cascade(&state), None);
+}
+
+#[test]
+fn the_incremental_solver_keeps_up_with_deduce_all() {
+    for seed in 0..20 {
+        let mut state = initial_state_seeded(9, 9, 12, seed);
+        let mut solver = IncrementalSolver::new(&state);
+        let mut moves = 0;
+        while state.status == GameStatus::InProgress && moves < 200 {
+            // Open what is known to be safe, flag what is known to be a
+            // mine, and guess when nothing is known
+            let next = if let Some(&point) = solver.safe().iter().min() {
+                open_cell(state.clone(), point)
+            } else if let Some(&point) = solver
+                .mines()
+                .iter()
+                .filter(|&&point| state.grid[point].state != CellState::Unopened(Flag::Sure))
+                .min()
+            {
+                change_flag(state.clone(), point, Flag::Sure)
+            } else {
+                let closed: Vec<Point> = state
+                    .grid
+                    .points()
+                    .filter(|&point| {
+                        state.grid[point].state == CellState::Unopened(Flag::Unflagged)
+                    })
+                    .collect();
+                open_cell(state.clone(), closed[moves * 7 % closed.len()])
+            };
+            solver.update(&next, &changed_cells(&state, &next));
+            state = next;
+            moves += 1;
+
+            let (safe, mines) = deduce_all(&state);
+            assert_eq!(solver.safe(), &safe, "seed {} move {}", seed, moves);
+            assert_eq!(solver.mines(), &mines, "seed {} move {}", seed, moves);
+        }
+    }
+}
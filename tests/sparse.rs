@@ -0,0 +1,67 @@
+use rusty_mines::*;
+use std::time::{Duration, Instant};
+
+#[test]
+fn a_huge_sparse_board_builds_quickly() {
+    let started = Instant::now();
+    let state = initial_state_sparse(10_000, 10_000, 100, 3).unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let mines = state.mine_positions();
+    assert_eq!(mines.len(), 100);
+    assert!(mines.iter().all(|&point| state.in_bounds(point)));
+    let (x, y) = mines[0];
+    assert_eq!(state.cell((x, y)).cell_type, CellType::Mine);
+    assert_eq!(
+        state.adjacent((x, y)).len(),
+        find_adjacent(x, y, 10_000, 10_000).len()
+    );
+    for adj in state.adjacent((x, y)) {
+        assert!(state.adjacent_mines(adj) >= 1);
+    }
+    assert_eq!(state.safe_cells_remaining(), 10_000 * 10_000 - 100);
+}
+
+#[test]
+fn a_sparse_board_plays_like_a_dense_one() {
+    for seed in 0..10 {
+        let dense = initial_state_seeded(12, 10, 15, seed);
+        let mut sparse = SparseState::from_mines(12, 10, &dense.mine_positions());
+        let mut dense = change_flag(dense, (0, 0), Flag::Unsure);
+        sparse = sparse.change_flag((0, 0), Flag::Unsure);
+        for point in [(6, 5), (0, 9), (11, 0), (3, 3)] {
+            dense = open_cell(dense, point);
+            sparse = sparse.open_cell(point);
+        }
+        for point in dense.grid.points() {
+            assert_eq!(
+                sparse.cell(point),
+                dense.grid[point],
+                "seed {} {:?}",
+                seed,
+                point
+            );
+        }
+        assert_eq!(sparse.status, dense.status, "seed {}", seed);
+        assert_eq!(sparse.safe_cells_remaining(), dense.safe_cells_remaining());
+    }
+}
+
+#[test]
+fn a_sparse_sure_flag_protects_its_cell() {
+    let state = SparseState::from_mines(3, 3, &[(0, 0)]).change_flag((0, 0), Flag::Sure);
+    let state = state.open_cell((0, 0));
+    assert_eq!(state.status, GameStatus::InProgress);
+    assert_eq!(state.cell((0, 0)).state, CellState::Unopened(Flag::Sure));
+
+    let state = state.change_flag((2, 2), Flag::Unsure).open_cell((2, 2));
+    assert_eq!(state.cell((2, 2)).state, CellState::Opened);
+}
+
+#[test]
+fn a_sparse_win_leaves_the_flags_alone() {
+    let state = SparseState::from_mines(3, 1, &[(2, 0)]).change_flag((2, 0), Flag::Unsure);
+    let state = state.open_cell((0, 0));
+    assert_eq!(state.status, GameStatus::Won);
+    assert_eq!(state.cell((2, 0)).state, CellState::Unopened(Flag::Unsure));
+}